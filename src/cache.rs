@@ -0,0 +1,309 @@
+use crate::config::CacheConfig;
+use crate::types::ValidationResult;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Name of the file results are kept in, under the cache directory
+const CACHE_FILE: &str = "validation-cache.json";
+
+/// Validation results from Jenkins, keyed by a hash of the content and Jenkins URL
+///
+/// Results expire after a TTL, and the oldest are dropped once there are too many.
+/// When persistence is enabled the whole cache is rewritten to disk after each new
+/// result; if several processes share the file, the last one to write wins.
+pub struct ValidationCache {
+    entries: Mutex<HashMap<String, Entry>>,
+    ttl_secs: u64,
+    max_entries: usize,
+    /// File to persist results to, if enabled
+    path: Option<PathBuf>,
+    /// Serialises writes to the cache file
+    write_lock: tokio::sync::Mutex<()>,
+}
+
+/// A cached validation result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    /// When the result was stored, in seconds since the Unix epoch
+    stored_at: u64,
+    /// Version of Jenkins that produced the result, if it said
+    jenkins_version: Option<String>,
+    result: ValidationResult,
+}
+
+impl ValidationCache {
+    /// Create a cache, loading any results persisted by earlier runs
+    pub fn new(config: &CacheConfig) -> Self {
+        let path = if config.persist {
+            config
+                .dir
+                .clone()
+                .or_else(|| dirs::cache_dir().map(|dir| dir.join("jenkinsfile-ls")))
+                .map(|dir| dir.join(CACHE_FILE))
+        } else {
+            None
+        };
+
+        let cache = Self {
+            entries: Mutex::new(path.as_deref().map(load).unwrap_or_default()),
+            ttl_secs: config.ttl_secs,
+            max_entries: config.max_entries,
+            path,
+            write_lock: tokio::sync::Mutex::new(()),
+        };
+        cache.prune(&mut cache.entries.lock().unwrap(), now());
+        cache
+    }
+
+    /// Cache key for validating `content` against the Jenkins at `jenkins_url`
+    pub fn key(jenkins_url: &str, content: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(jenkins_url.trim_end_matches('/').as_bytes());
+        hasher.update([0]);
+        hasher.update(content.as_bytes());
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Look up a result
+    ///
+    /// Expired results are ignored, as are ones from a different Jenkins version,
+    /// since an upgrade can change what is valid. Results stored without a version
+    /// (e.g. behind a proxy that drops the `X-Jenkins` header) are only reused while
+    /// the version is still unknown, and vice versa.
+    pub fn get(&self, key: &str, jenkins_version: Option<&str>) -> Option<ValidationResult> {
+        let entries = self.entries.lock().unwrap();
+        let entry = entries.get(key)?;
+        if self.is_expired(entry, now()) || entry.jenkins_version.as_deref() != jenkins_version {
+            return None;
+        }
+        Some(entry.result.clone())
+    }
+
+    /// Store a result, persisting the cache if enabled
+    ///
+    /// Failing to write the cache file is logged rather than reported, since the
+    /// result itself is still good.
+    pub async fn insert(
+        &self,
+        key: String,
+        jenkins_version: Option<String>,
+        result: ValidationResult,
+    ) {
+        let snapshot = {
+            let mut entries = self.entries.lock().unwrap();
+            let now = now();
+            entries.insert(
+                key,
+                Entry {
+                    stored_at: now,
+                    jenkins_version,
+                    result,
+                },
+            );
+            self.prune(&mut entries, now);
+            self.path.as_ref().map(|_| serde_json::to_vec(&*entries))
+        };
+
+        if let (Some(path), Some(snapshot)) = (&self.path, snapshot) {
+            let _guard = self.write_lock.lock().await;
+            let saved = match snapshot {
+                Ok(snapshot) => save(path, &snapshot).await,
+                Err(e) => Err(e.into()),
+            };
+            if let Err(e) = saved {
+                tracing::warn!("Failed to write cache file {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    fn is_expired(&self, entry: &Entry, now: u64) -> bool {
+        now.saturating_sub(entry.stored_at) >= self.ttl_secs
+    }
+
+    /// Drop expired results, then the oldest ones until within the size limit
+    fn prune(&self, entries: &mut HashMap<String, Entry>, now: u64) {
+        entries.retain(|_, entry| !self.is_expired(entry, now));
+        if entries.len() > self.max_entries {
+            let mut by_age: Vec<(u64, String)> = entries
+                .iter()
+                .map(|(key, entry)| (entry.stored_at, key.clone()))
+                .collect();
+            by_age.sort();
+            let excess = entries.len() - self.max_entries;
+            for (_, key) in by_age.into_iter().take(excess) {
+                entries.remove(&key);
+            }
+        }
+    }
+}
+
+/// Read persisted results, starting afresh if the file is missing or unreadable
+fn load(path: &Path) -> HashMap<String, Entry> {
+    let contents = match std::fs::read(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return HashMap::new(),
+        Err(e) => {
+            tracing::warn!("Failed to read cache file {}: {}", path.display(), e);
+            return HashMap::new();
+        }
+    };
+    serde_json::from_slice(&contents).unwrap_or_else(|e| {
+        tracing::warn!("Ignoring corrupt cache file {}: {}", path.display(), e);
+        HashMap::new()
+    })
+}
+
+/// Write the cache file, via a temporary file so readers never see half of it
+async fn save(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let temporary = path.with_extension(format!("{}.tmp", std::process::id()));
+    tokio::fs::write(&temporary, contents).await?;
+    tokio::fs::rename(&temporary, path).await
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn in_memory(max_entries: usize) -> ValidationCache {
+        ValidationCache::new(&CacheConfig {
+            persist: false,
+            max_entries,
+            ..Default::default()
+        })
+    }
+
+    fn error(message: &str) -> ValidationResult {
+        ValidationResult::Error(message.to_string())
+    }
+
+    #[test]
+    fn test_key_depends_on_url_and_content() {
+        let key = ValidationCache::key("https://jenkins.example.com", "node {}");
+        assert_eq!(key.len(), 64);
+        assert_eq!(
+            key,
+            ValidationCache::key("https://jenkins.example.com/", "node {}")
+        );
+        assert_ne!(
+            key,
+            ValidationCache::key("https://ci.example.com", "node {}")
+        );
+        assert_ne!(
+            key,
+            ValidationCache::key("https://jenkins.example.com", "node { }")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_respects_jenkins_version() {
+        let cache = in_memory(10);
+        cache
+            .insert("a".to_string(), Some("2.440".to_string()), error("boom"))
+            .await;
+
+        assert_eq!(cache.get("a", Some("2.440")), Some(error("boom")));
+        assert_eq!(cache.get("a", Some("2.441")), None);
+        assert_eq!(cache.get("b", Some("2.440")), None);
+
+        // An unknown version might be a different one
+        assert_eq!(cache.get("a", None), None);
+        cache
+            .insert("c".to_string(), None, ValidationResult::Success)
+            .await;
+        assert_eq!(cache.get("c", Some("2.440")), None);
+        // Without versions at all, results are still reused
+        assert_eq!(cache.get("c", None), Some(ValidationResult::Success));
+    }
+
+    #[tokio::test]
+    async fn test_expired_entries_are_ignored() {
+        let cache = ValidationCache::new(&CacheConfig {
+            persist: false,
+            ttl_secs: 0,
+            ..Default::default()
+        });
+        cache
+            .insert(
+                "a".to_string(),
+                Some("2.440".to_string()),
+                ValidationResult::Success,
+            )
+            .await;
+        assert_eq!(cache.get("a", Some("2.440")), None);
+    }
+
+    #[test]
+    fn test_prune_drops_oldest() {
+        let cache = in_memory(2);
+        let now = now();
+        let mut entries: HashMap<String, Entry> = [("old", 3), ("middle", 2), ("new", 1)]
+            .into_iter()
+            .map(|(key, age)| {
+                let entry = Entry {
+                    stored_at: now - age,
+                    jenkins_version: None,
+                    result: ValidationResult::Success,
+                };
+                (key.to_string(), entry)
+            })
+            .collect();
+
+        cache.prune(&mut entries, now);
+        let mut kept: Vec<&str> = entries.keys().map(String::as_str).collect();
+        kept.sort();
+        assert_eq!(kept, vec!["middle", "new"]);
+    }
+
+    #[tokio::test]
+    async fn test_persists_across_instances() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = CacheConfig {
+            dir: Some(dir.path().join("nested")),
+            ..Default::default()
+        };
+
+        let cache = ValidationCache::new(&config);
+        cache
+            .insert(
+                "a".to_string(),
+                Some("2.440".to_string()),
+                ValidationResult::Success,
+            )
+            .await;
+
+        let reloaded = ValidationCache::new(&config);
+        assert_eq!(
+            reloaded.get("a", Some("2.440")),
+            Some(ValidationResult::Success)
+        );
+    }
+
+    #[test]
+    fn test_corrupt_file_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(CACHE_FILE), "not json").unwrap();
+        let cache = ValidationCache::new(&CacheConfig {
+            dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        });
+        assert_eq!(cache.get("a", None), None);
+    }
+}
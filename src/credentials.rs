@@ -0,0 +1,346 @@
+use crate::types::{LspError, Result};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// Longest a credential command may run, e.g. waiting for a passphrase to be entered
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often to check whether a credential command has finished
+const COMMAND_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Output of credential commands that have already run, keyed by command
+///
+/// Helpers like `pass` may prompt for a passphrase, so each command runs at most
+/// once per session, however often the configuration is loaded.
+static COMMAND_OUTPUT: LazyLock<Mutex<HashMap<String, String>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Run a credential helper command through the shell and return what it printed
+///
+/// Surrounding whitespace (such as the trailing newline) is removed. The command
+/// fails if it can't be started, exits unsuccessfully, prints nothing or is still
+/// running after `COMMAND_TIMEOUT`.
+pub fn run_command(command: &str) -> Result<String> {
+    run_command_with_timeout(command, COMMAND_TIMEOUT)
+}
+
+fn run_command_with_timeout(command: &str, timeout: Duration) -> Result<String> {
+    if let Some(output) = COMMAND_OUTPUT.lock().unwrap().get(command) {
+        return Ok(output.clone());
+    }
+
+    tracing::debug!("Running credential command: {}", command);
+    let mut child = shell(command)
+        // stdin and stdout may be the LSP connection, so keep the command off them
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| LspError::Config(format!("Failed to run '{}': {}", command, e)))?;
+
+    // Read the output as it comes, so the command can't block on a full pipe
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        let status = child
+            .try_wait()
+            .map_err(|e| LspError::Config(format!("Failed to run '{}': {}", command, e)))?;
+        if let Some(status) = status {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(LspError::Config(format!(
+                "'{}' didn't finish within {} seconds",
+                command,
+                timeout.as_secs_f32()
+            )));
+        }
+        std::thread::sleep(COMMAND_POLL_INTERVAL);
+    };
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr);
+        let stderr = stderr.trim();
+        return Err(LspError::Config(if stderr.is_empty() {
+            format!("'{}' failed ({})", command, status)
+        } else {
+            format!("'{}' failed ({}): {}", command, status, stderr)
+        }));
+    }
+
+    let stdout = String::from_utf8(stdout)
+        .map_err(|_| LspError::Config(format!("'{}' printed invalid UTF-8", command)))?;
+    let value = stdout.trim();
+    if value.is_empty() {
+        return Err(LspError::Config(format!("'{}' printed nothing", command)));
+    }
+
+    COMMAND_OUTPUT
+        .lock()
+        .unwrap()
+        .insert(command.to_string(), value.to_string());
+    Ok(value.to_string())
+}
+
+/// Read all of a pipe on another thread
+fn read_in_background(
+    pipe: Option<impl Read + Send + 'static>,
+) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut output = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut output);
+        }
+        output
+    })
+}
+
+/// Read an API token from a file, such as one mounted into a dev container
+///
+/// A leading `~/` is the home directory. Warns if other users can read the file.
+pub fn read_token_file(path: &Path) -> Result<String> {
+    let path = expand_home(path);
+    let contents = std::fs::read_to_string(&path).map_err(|e| {
+        LspError::Config(format!(
+            "Failed to read token file {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    warn_if_world_readable(&path);
+
+    let token = contents.trim();
+    if token.is_empty() {
+        return Err(LspError::Config(format!(
+            "Token file {} is empty",
+            path.display()
+        )));
+    }
+    Ok(token.to_string())
+}
+
+/// Credentials for a host from the user's netrc file
+#[derive(Debug, Default, PartialEq)]
+pub struct NetrcEntry {
+    pub login: Option<String>,
+    pub password: Option<String>,
+}
+
+/// Look up the credentials for `host` in `$NETRC`, or else `~/.netrc`
+///
+/// A missing or unreadable file is treated as having no entry.
+pub fn netrc_lookup(host: &str) -> Option<NetrcEntry> {
+    let path = netrc_path()?;
+    let contents = std::fs::read_to_string(&path).ok()?;
+    let entry = parse_netrc(&contents, host)?;
+    tracing::debug!("Using credentials for {} from {}", host, path.display());
+    warn_if_world_readable(&path);
+    Some(entry)
+}
+
+/// The netrc file in use, if any
+pub fn netrc_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("NETRC") {
+        return Some(PathBuf::from(path));
+    }
+    let home = dirs::home_dir()?;
+    [".netrc", "_netrc"]
+        .into_iter()
+        .map(|name| home.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Find the entry for `host` in netrc contents, falling back to the `default` entry
+///
+/// `macdef` definitions are skipped, up to the blank line that ends them.
+fn parse_netrc(contents: &str, host: &str) -> Option<NetrcEntry> {
+    let mut tokens = Vec::new();
+    let mut lines = contents.lines();
+    while let Some(line) = lines.next() {
+        for word in line.split_whitespace() {
+            if word.starts_with('#') {
+                break;
+            }
+            if word == "macdef" {
+                lines
+                    .by_ref()
+                    .take_while(|line| !line.trim().is_empty())
+                    .for_each(drop);
+                break;
+            }
+            tokens.push(word);
+        }
+    }
+
+    // The entry being read, and whether its machine is `host` (None for `default`)
+    let mut current: Option<(Option<bool>, NetrcEntry)> = None;
+    let mut machine = None;
+    let mut default = None;
+    let mut finish = |current: Option<(Option<bool>, NetrcEntry)>| match current {
+        Some((Some(true), entry)) if machine.is_none() => machine = Some(entry),
+        Some((None, entry)) if default.is_none() => default = Some(entry),
+        _ => {}
+    };
+
+    let mut tokens = tokens.into_iter();
+    while let Some(token) = tokens.next() {
+        match token {
+            "machine" => {
+                finish(current.take());
+                let matches = tokens
+                    .next()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(host));
+                current = Some((Some(matches), NetrcEntry::default()));
+            }
+            "default" => {
+                finish(current.take());
+                current = Some((None, NetrcEntry::default()));
+            }
+            "login" | "password" | "account" => {
+                let value = tokens.next().map(str::to_string);
+                if let Some((_, entry)) = &mut current {
+                    match token {
+                        "login" => entry.login = value,
+                        "password" => entry.password = value,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    finish(current);
+    machine.or(default)
+}
+
+/// Resolve a leading `~` to the home directory
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Warn if a file holding credentials can be read by other users
+#[cfg(unix)]
+fn warn_if_world_readable(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+    if let Ok(metadata) = std::fs::metadata(path)
+        && metadata.permissions().mode() & 0o004 != 0
+    {
+        tracing::warn!(
+            "{} is readable by every user; consider `chmod 600 {}`",
+            path.display(),
+            path.display()
+        );
+    }
+}
+
+#[cfg(not(unix))]
+fn warn_if_world_readable(_path: &Path) {}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_command_trims_output() {
+        assert_eq!(run_command("echo  secret-token ").unwrap(), "secret-token");
+    }
+
+    #[test]
+    fn test_run_command_runs_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("runs");
+        let command = format!("echo ran >> {} && echo token", log.display());
+
+        assert_eq!(run_command(&command).unwrap(), "token");
+        assert_eq!(run_command(&command).unwrap(), "token");
+        assert_eq!(std::fs::read_to_string(&log).unwrap().lines().count(), 1);
+    }
+
+    #[test]
+    fn test_run_command_errors() {
+        let failed = run_command("echo denied >&2 && exit 3").unwrap_err();
+        assert!(matches!(failed, LspError::Config(_)));
+        assert!(failed.to_string().contains("denied"), "{}", failed);
+
+        let empty = run_command("exit 0").unwrap_err();
+        assert!(empty.to_string().contains("printed nothing"), "{}", empty);
+    }
+
+    #[test]
+    fn test_run_command_times_out() {
+        let started = Instant::now();
+        let hung = run_command_with_timeout("exec sleep 10", Duration::from_millis(200));
+        assert!(matches!(hung, Err(LspError::Config(_))));
+        assert!(hung.unwrap_err().to_string().contains("didn't finish"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_read_token_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token");
+        std::fs::write(&path, "secret-token\n").unwrap();
+        assert_eq!(read_token_file(&path).unwrap(), "secret-token");
+
+        std::fs::write(&path, "\n").unwrap();
+        assert!(matches!(read_token_file(&path), Err(LspError::Config(_))));
+        assert!(read_token_file(&dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_parse_netrc() {
+        let netrc = "\
+# Personal machines
+machine github.com login me password gh-token
+machine jenkins.example.com
+    login jenkins-user
+    password jenkins-token
+macdef init
+    cd /pub
+    machine ignored.example.com login nobody
+
+default login anonymous password guest
+";
+        assert_eq!(
+            parse_netrc(netrc, "JENKINS.example.com"),
+            Some(NetrcEntry {
+                login: Some("jenkins-user".to_string()),
+                password: Some("jenkins-token".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_netrc(netrc, "ignored.example.com"),
+            Some(NetrcEntry {
+                login: Some("anonymous".to_string()),
+                password: Some("guest".to_string()),
+            })
+        );
+        assert_eq!(parse_netrc("machine a login b", "c"), None);
+    }
+}
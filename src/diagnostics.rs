@@ -1,73 +1,502 @@
 use regex::Regex;
-use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
+use std::sync::LazyLock;
+use tower_lsp::lsp_types::{
+    CodeDescription, Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range, Url,
+};
 
 const SUCCESS_MESSAGE: &str = "Jenkinsfile successfully validated.";
 
+/// The Pipeline Syntax documentation, which diagnostic codes link into
+const SYNTAX_DOCS: &str = "https://www.jenkins.io/doc/book/pipeline/syntax/";
+
+/// Distance between tab stops in Jenkins' column numbers
+const TAB_WIDTH: u32 = 8;
+
+/// Most lines of unrecognised output to show in a diagnostic
+const MAX_EXCERPT_LINES: usize = 10;
+/// Most characters of unrecognised output to show in a diagnostic
+const MAX_EXCERPT_CHARS: usize = 500;
+
+/// Start of an error: `WorkflowScript: <line>: <message>`
+static ENTRY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*WorkflowScript:\s+(\d+):\s*(.*)$").unwrap());
+
+/// End of an error's message, giving its location: `@ line[:] <line>, column[:] <col>[.]`
+static LOCATION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(.*?)\s*@\s*line[:\s]+(\d+),\s*column[:\s]+(\d+)\.?\s*$").unwrap()
+});
+
+/// Lines that frame the errors rather than being part of them: headers, the error
+/// count, and the stack trace of the exception that carried them
+static FRAMING: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?x)
+        startup\ failed:\s*$
+        | ^\s*Errors\ encountered\ validating\ Jenkinsfile:\s*$
+        | ^\s*\d+\ errors?\s*$
+        | ^\s+at\ \S+\(.*\)\s*$
+        | ^\s*\.\.\.\ \d+\ more\s*$",
+    )
+    .unwrap()
+});
+
+/// Elements of an HTML page whose content isn't text
+static HTML_CODE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?is)<script\b.*?</script\s*>|<style\b.*?</style\s*>|<!--.*?-->").unwrap()
+});
+
+static HTML_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+
+/// A kind of error Jenkins reports
+struct Kind {
+    /// Stable code for the kind of error
+    code: &'static str,
+    /// Pattern matching its messages
+    pattern: Regex,
+    /// Section of the syntax documentation about it, which may refer to groups of
+    /// `pattern`
+    anchor: &'static str,
+}
+
+/// Kinds of error, tried in order
+static KINDS: LazyLock<Vec<Kind>> = LazyLock::new(|| {
+    [
+        (
+            "unexpected-token",
+            r"(?i)^(unexpected (token|char|input)|expecting .*, found )",
+            "declarative-pipeline",
+        ),
+        (
+            "unknown-step",
+            r#"^(No such DSL method '|Unknown step |Invalid step ")"#,
+            "declarative-steps",
+        ),
+        (
+            "invalid-step-arguments",
+            r#"^(Missing required parameter|Invalid parameter "|Can't mix named and unnamed)"#,
+            "declarative-steps",
+        ),
+        ("expected-step", r"^Expected a step", "declarative-steps"),
+        ("script-required", r#"outside "script" blocks"#, "script"),
+        (
+            "invalid-agent-type",
+            r"^(Invalid agent type|No agent type specified)",
+            "agent",
+        ),
+        (
+            "missing-section",
+            r#"^Missing required section "(agent|stages|steps)""#,
+            "$1",
+        ),
+        (
+            "missing-section",
+            r"^Missing required section",
+            "declarative-sections",
+        ),
+        ("no-stages", r"^No stages specified", "stages"),
+        (
+            "unknown-section",
+            r"^Undefined section ",
+            "declarative-sections",
+        ),
+        ("unknown-section", r"^Unknown stage section ", "stage"),
+        (
+            "duplicate-section",
+            r"^Multiple occurrences of the ",
+            "declarative-sections",
+        ),
+        (
+            "invalid-stage",
+            r#"^(Expected a stage|Expected one of "steps"|Only one of "matrix"|Nothing to execute within stage)"#,
+            "stage",
+        ),
+        (
+            "unknown-when-condition",
+            r"^Unknown (conditional|when condition)",
+            "when",
+        ),
+        ("invalid-post-condition", r#"^Invalid condition ""#, "post"),
+        ("invalid-option", r#"^Invalid option type ""#, "options"),
+        (
+            "invalid-parameter-type",
+            r#"^Invalid parameter type ""#,
+            "parameters",
+        ),
+        ("invalid-trigger", r#"^Invalid trigger type ""#, "triggers"),
+        ("invalid-tool", r#"^Invalid tool type ""#, "tools"),
+        ("invalid-environment", r"^Environment variable", "environment"),
+    ]
+    .into_iter()
+    .map(|(code, pattern, anchor)| Kind {
+        code,
+        pattern: Regex::new(pattern).unwrap(),
+        anchor,
+    })
+    .collect()
+});
+
 /// Parse Jenkins validation response and convert to LSP diagnostics
 ///
-/// Jenkins returns errors in various formats:
-/// - "WorkflowScript: 46: unexpected token: } @ line 46, column 1."
-/// - "WorkflowScript: 46: unexpected token: } @ line: 46, column: 1"
+/// Jenkins reports each error as `WorkflowScript: <line>: <message> @ line <line>,
+/// column <column>.` (sometimes with colons after `line` and `column`), where the
+/// message may span several lines and is followed by an excerpt of the source with
+/// a caret under the error. The errors may be wrapped in a `startup failed:` header,
+/// an `N errors` footer and the stack trace of a `MultipleCompilationErrorsException`.
 ///
-/// Multiple errors may be present across multiple lines of output.
-pub fn parse_jenkins_response(response: &str) -> Vec<Diagnostic> {
+/// Each diagnostic covers the token at the location Jenkins gave in `content`, the
+/// text that was validated.
+///
+/// Any other text is reported (shortened, and without HTML markup) as a diagnostic
+/// for the whole file, as is a failure without any text, so that a failure is never
+/// mistaken for a clean file.
+pub fn parse_jenkins_response(response: &str, content: &str) -> Vec<Diagnostic> {
     // Check for success message first
     if response.contains(SUCCESS_MESSAGE) {
         return Vec::new();
     }
 
-    // Regex pattern to match Jenkins error format
-    // WorkflowScript: <num>: <message> @ line[:]? <line>, column[:]? <col>[.]?
-    // Handles variations with/without colons after line/column and optional trailing period
-    let re =
-        Regex::new(r"WorkflowScript:\s+\d+:\s+(.+?)\s+@\s*line[:\s]+(\d+),\s*column[:\s]+(\d+)\.?")
-            .expect("Invalid regex pattern");
+    let mut parser = Parser {
+        state: State::default(),
+        lines: content.lines().collect(),
+        diagnostics: Vec::new(),
+        unrecognised: Vec::new(),
+    };
+    for line in response.lines() {
+        parser.line(line);
+    }
+    parser.finish()
+}
+
+/// State of the parser between lines of Jenkins' output
+#[derive(Default)]
+enum State {
+    /// Outside any error
+    #[default]
+    Between,
+    /// Reading an error's message, which hasn't given its location yet
+    Message(Message),
+    /// Skipping the source excerpt after an error, up to the next blank line
+    Excerpt,
+}
 
-    let mut diagnostics = Vec::new();
+/// An error whose message is still being read
+struct Message {
+    /// Line number Jenkins gave in the `WorkflowScript: <line>:` prefix
+    line: u32,
+    lines: Vec<String>,
+}
 
-    for line in response.lines() {
-        if let Some(captures) = re.captures(line) {
-            // Extract message, line number, and column number
-            let message = captures
-                .get(1)
-                .map(|m| m.as_str())
-                .unwrap_or("Unknown error");
-            let line_str = captures.get(2).map(|m| m.as_str()).unwrap_or("0");
-            let col_str = captures.get(3).map(|m| m.as_str()).unwrap_or("0");
-
-            // Parse line and column numbers
-            if let (Ok(line_num), Ok(col_num)) = (line_str.parse::<u32>(), col_str.parse::<u32>()) {
-                // LSP uses 0-indexed line and column numbers
-                let line = line_num.saturating_sub(1);
-                let col = col_num.saturating_sub(1);
-
-                let diagnostic = Diagnostic {
-                    range: Range {
-                        start: Position {
-                            line,
-                            character: col,
-                        },
-                        end: Position {
-                            line,
-                            character: col,
-                        },
-                    },
-                    severity: Some(DiagnosticSeverity::ERROR),
-                    code: None,
-                    code_description: None,
-                    source: Some("jenkinsfile-ls".to_string()),
-                    message: message.to_string(),
-                    related_information: None,
-                    tags: None,
-                    data: None,
-                };
+struct Parser<'a> {
+    state: State,
+    /// Lines of the validated text
+    lines: Vec<&'a str>,
+    diagnostics: Vec<Diagnostic>,
+    /// Text that isn't part of any error Jenkins located
+    unrecognised: Vec<String>,
+}
+
+impl Parser<'_> {
+    fn line(&mut self, line: &str) {
+        if let Some(captures) = ENTRY.captures(line) {
+            self.end_message();
+            let message = Message {
+                line: captures[1].parse().unwrap_or(0),
+                lines: Vec::new(),
+            };
+            self.state = State::Message(message);
+            self.message_line(&captures[2]);
+            return;
+        }
+
+        match &mut self.state {
+            State::Message(message) => {
+                if line.trim().is_empty() {
+                    self.end_message();
+                    self.state = State::Between;
+                } else if line.trim() == "^" {
+                    // The message had no location, and what came before was the excerpt
+                    message.lines.pop();
+                    self.end_message();
+                    self.state = State::Excerpt;
+                } else {
+                    self.message_line(line);
+                }
+            }
+            State::Excerpt => {
+                if line.trim().is_empty() {
+                    self.state = State::Between;
+                }
+            }
+            State::Between => {
+                if !line.trim().is_empty() && !FRAMING.is_match(line) {
+                    self.unrecognised.push(line.trim().to_string());
+                }
+            }
+        }
+    }
+
+    /// Add a line to the current message, finishing it if the line gives its location
+    fn message_line(&mut self, line: &str) {
+        let State::Message(message) = &mut self.state else {
+            return;
+        };
+        match LOCATION.captures(line) {
+            Some(captures) => {
+                message.lines.push(captures[1].trim().to_string());
+                let line = captures[2].parse().unwrap_or(message.line);
+                let column = captures[3].parse().unwrap_or(1);
+                let text = join(&message.lines);
+                self.push(text, line, column);
+                self.state = State::Excerpt;
+            }
+            None => message.lines.push(line.trim().to_string()),
+        }
+    }
+
+    /// Report a message that ended without a location, at the line from its prefix
+    fn end_message(&mut self) {
+        if let State::Message(message) = std::mem::take(&mut self.state) {
+            let text = join(&message.lines);
+            let line = message.line;
+            self.push(text, line, 1);
+        }
+    }
+
+    /// Add a diagnostic at a 1-based line and column from Jenkins
+    fn push(&mut self, message: String, line: u32, column: u32) {
+        let message = if message.is_empty() {
+            "Unknown error".to_string()
+        } else {
+            message
+        };
+        let range = token_range(&self.lines, line, column);
+        self.diagnostics.push(error(message, range));
+    }
+
+    fn finish(mut self) -> Vec<Diagnostic> {
+        self.end_message();
+        if !self.unrecognised.is_empty() {
+            let message = format!(
+                "Jenkins returned an unrecognised error:\n{}",
+                excerpt(&self.unrecognised.join("\n"))
+            );
+            self.diagnostics.push(error(message, Range::default()));
+        }
+        if self.diagnostics.is_empty() {
+            let message = "Jenkins rejected the Jenkinsfile without saying why".to_string();
+            self.diagnostics.push(error(message, Range::default()));
+        }
+        self.diagnostics
+    }
+}
+
+/// Range of the token at a 1-based line and column from Jenkins
+///
+/// Groovy counts columns in UTF-16 code units, as LSP does, except that a tab moves
+/// to the next tab stop. The range covers an identifier, string literal or single
+/// symbol after any spaces, or the whole header of a stage (`stage('Build')`).
+/// Without the line in `lines`, it is empty.
+fn token_range(lines: &[&str], line: u32, column: u32) -> Range {
+    // LSP uses 0-indexed line and column numbers
+    let line_index = line.saturating_sub(1);
+    let Some(text) = lines.get(line_index as usize) else {
+        let position = Position {
+            line: line_index,
+            character: column.saturating_sub(1),
+        };
+        return Range {
+            start: position,
+            end: position,
+        };
+    };
+
+    // Jenkins sometimes points at the space before a token, or only gives its line
+    let offset = byte_offset(text, column);
+    let start = offset + text[offset..].len() - text[offset..].trim_start().len();
+    let end = start + token_len(&text[start..]);
+    let position = |offset: usize| Position {
+        line: line_index,
+        character: text[..offset].encode_utf16().count() as u32,
+    };
+    Range {
+        start: position(start),
+        end: position(end),
+    }
+}
+
+/// Byte offset in `text` of a 1-based Groovy column
+fn byte_offset(text: &str, column: u32) -> usize {
+    let mut current = 1;
+    for (offset, c) in text.char_indices() {
+        if current >= column {
+            return offset;
+        }
+        current = if c == '\t' {
+            (current - 1) / TAB_WIDTH * TAB_WIDTH + TAB_WIDTH + 1
+        } else {
+            current + c.len_utf16() as u32
+        };
+    }
+    text.len()
+}
+
+/// Length in bytes of the token at the start of `text`
+fn token_len(text: &str) -> usize {
+    let Some(first) = text.chars().next() else {
+        return 0;
+    };
+    if is_identifier_char(first) && !first.is_ascii_digit() {
+        let end = text
+            .find(|c: char| !is_identifier_char(c))
+            .unwrap_or(text.len());
+        if &text[..end] == "stage"
+            && let Some(header) = stage_header_len(text, end)
+        {
+            return header;
+        }
+        end
+    } else if first == '\'' || first == '"' {
+        string_len(text, first)
+    } else {
+        first.len_utf8()
+    }
+}
 
-                diagnostics.push(diagnostic);
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// Length of a string literal at the start of `text`, up to the end of the line if
+/// it isn't closed there
+fn string_len(text: &str, quote: char) -> usize {
+    let triple: String = [quote; 3].iter().collect();
+    if let Some(rest) = text.strip_prefix(triple.as_str()) {
+        return rest
+            .find(triple.as_str())
+            .map_or(text.len(), |end| 3 + end + 3);
+    }
+
+    let mut escaped = false;
+    for (offset, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            _ if c == quote => return offset + c.len_utf8(),
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// Length of `stage(...)` at the start of `text`, given where `stage` ends
+fn stage_header_len(text: &str, mut offset: usize) -> Option<usize> {
+    offset += text[offset..].len() - text[offset..].trim_start().len();
+    if !text[offset..].starts_with('(') {
+        return None;
+    }
+    let mut depth = 0;
+    while let Some(c) = text[offset..].chars().next() {
+        match c {
+            '\'' | '"' => {
+                offset += string_len(&text[offset..], c);
+                continue;
             }
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(offset + 1);
+                }
+            }
+            _ => {}
         }
+        offset += c.len_utf8();
+    }
+    None
+}
+
+/// Code and documentation link for an error message, if it is of a known kind
+///
+/// The local checks use the same messages as Jenkins, so they share the codes.
+pub fn classify(message: &str) -> Option<(NumberOrString, CodeDescription)> {
+    KINDS.iter().find_map(|kind| {
+        let captures = kind.pattern.captures(message)?;
+        let mut anchor = String::new();
+        captures.expand(kind.anchor, &mut anchor);
+        let href = Url::parse(&format!("{}#{}", SYNTAX_DOCS, anchor)).ok()?;
+        Some((
+            NumberOrString::String(kind.code.to_string()),
+            CodeDescription { href },
+        ))
+    })
+}
+
+/// A short, plain-text excerpt of output from Jenkins, fit to show to the user
+///
+/// HTML markup is removed, blank lines and control characters dropped, and the text
+/// cut off after a few lines.
+pub fn excerpt(text: &str) -> String {
+    let text = HTML_CODE.replace_all(text, "");
+    let text = HTML_TAG.replace_all(&text, "\n");
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+
+    let lines: Vec<String> = text
+        .lines()
+        .map(|line| {
+            line.split(|c: char| c.is_whitespace() || c.is_control())
+                .filter(|word| !word.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    let mut excerpt = lines
+        .iter()
+        .take(MAX_EXCERPT_LINES)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut truncated = lines.len() > MAX_EXCERPT_LINES;
+    if let Some((end, _)) = excerpt.char_indices().nth(MAX_EXCERPT_CHARS) {
+        excerpt.truncate(end);
+        truncated = true;
+    }
+    if truncated {
+        excerpt.push('…');
     }
+    excerpt
+}
 
-    diagnostics
+/// Join the non-empty lines of a message
+fn join(lines: &[String]) -> String {
+    lines
+        .iter()
+        .filter(|line| !line.is_empty())
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn error(message: String, range: Range) -> Diagnostic {
+    let (code, code_description) = classify(&message).unzip();
+    Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::ERROR),
+        code,
+        code_description,
+        source: Some("jenkinsfile-ls".to_string()),
+        message,
+        related_information: None,
+        tags: None,
+        data: None,
+    }
 }
 
 #[cfg(test)]
@@ -77,14 +506,14 @@ mod tests {
     #[test]
     fn test_parse_success_message() {
         let response = "Jenkinsfile successfully validated.";
-        let diagnostics = parse_jenkins_response(response);
+        let diagnostics = parse_jenkins_response(response, "");
         assert!(diagnostics.is_empty());
     }
 
     #[test]
     fn test_parse_single_error() {
         let response = "WorkflowScript: 46: unexpected token: } @ line 46, column 1.";
-        let diagnostics = parse_jenkins_response(response);
+        let diagnostics = parse_jenkins_response(response, "");
 
         assert_eq!(diagnostics.len(), 1);
         assert_eq!(diagnostics[0].message, "unexpected token: }");
@@ -102,7 +531,7 @@ Some other output line
 WorkflowScript: 20: Missing closing brace @ line 20, column 3.
         "#;
 
-        let diagnostics = parse_jenkins_response(response);
+        let diagnostics = parse_jenkins_response(response, "");
 
         assert_eq!(diagnostics.len(), 2);
 
@@ -118,16 +547,214 @@ WorkflowScript: 20: Missing closing brace @ line 20, column 3.
     }
 
     #[test]
-    fn test_parse_no_errors() {
+    fn test_parse_unrecognised_output() {
+        // Not a success, so reported for the whole file rather than dropped
         let response = "Some random output\nwithout any errors";
-        let diagnostics = parse_jenkins_response(response);
-        assert!(diagnostics.is_empty());
+        let diagnostics = parse_jenkins_response(response, "");
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "Jenkins returned an unrecognised error:\nSome random output\nwithout any errors"
+        );
+        assert_eq!(diagnostics[0].range.start, Position::default());
+
+        let empty = parse_jenkins_response("  \n", "");
+        assert_eq!(empty.len(), 1);
+        assert_eq!(empty[0].range.start, Position::default());
+    }
+
+    #[test]
+    fn test_parse_compilation_errors() {
+        let response = "\
+org.codehaus.groovy.control.MultipleCompilationErrorsException: startup failed:
+WorkflowScript: 3: Expected a stage @ line 3, column 9.
+           stages {
+           ^
+
+WorkflowScript: 7: Invalid condition \"sometimes\" - valid conditions are
+[always, changed, failure, success] @ line 7, column 13.
+               sometimes {
+               ^
+
+WorkflowScript: 12: Missing required section \"agent\"
+
+2 errors
+
+\tat org.codehaus.groovy.control.ErrorCollector.failIfErrors(ErrorCollector.java:309)
+\tat org.codehaus.groovy.control.CompilationUnit.applyToPrimaryClassNodes(CompilationUnit.java:1085)
+\t... 42 more
+";
+        let diagnostics = parse_jenkins_response(response, "");
+
+        let found: Vec<(&str, u32, u32)> = diagnostics
+            .iter()
+            .map(|d| {
+                let start = d.range.start;
+                (d.message.as_str(), start.line, start.character)
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("Expected a stage", 2, 8),
+                (
+                    "Invalid condition \"sometimes\" - valid conditions are\n[always, changed, failure, success]",
+                    6,
+                    12
+                ),
+                ("Missing required section \"agent\"", 11, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_exception_without_errors() {
+        let response = "\
+java.lang.IllegalStateException: Pipeline model converter crashed
+\tat org.jenkinsci.plugins.pipeline.modeldefinition.Converter.convert(Converter.java:42)
+";
+        let diagnostics = parse_jenkins_response(response, "");
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "Jenkins returned an unrecognised error:\n\
+             java.lang.IllegalStateException: Pipeline model converter crashed"
+        );
+    }
+
+    #[test]
+    fn test_excerpt() {
+        let page = "<!DOCTYPE html><html><head><title>Jenkins &amp; friends</title>\
+            <style>body { color: red }</style><script>var x = '<b>';</script></head>\
+            <body><p>Oops!\tSomething\u{7}  went wrong.</p></body></html>";
+        assert_eq!(
+            excerpt(page),
+            "Jenkins & friends\nOops! Something went wrong."
+        );
+
+        let long = "line\n".repeat(20);
+        assert_eq!(excerpt(&long), format!("{}…", ["line"; 10].join("\n")));
+        let wide = "é".repeat(600);
+        assert_eq!(excerpt(&wide).chars().count(), MAX_EXCERPT_CHARS + 1);
+    }
+
+    #[test]
+    fn test_token_ranges() {
+        let content = "\
+pipeline {
+\tagent { label 'linux' }
+    stages {
+        stage(\"Build (\\\"fast\\\")\") { steps { sh 'make' } }
+        // 😀 é
+        echo '''done'''
+    }
+}";
+        let response = "\
+WorkflowScript: 2: Invalid agent type \"label\" @ line 2, column 17.
+WorkflowScript: 2: Expected a string @ line 2, column 23.
+WorkflowScript: 4: Nothing to execute @ line 4, column 9.
+WorkflowScript: 4: Unexpected brace @ line 4, column 35.
+WorkflowScript: 6: Unexpected string @ line 6, column 14.
+WorkflowScript: 5: Unknown word @ line 5, column 15.
+WorkflowScript: 9: Past the end @ line 9, column 3.
+WorkflowScript: 3: Missing required section \"agent\"
+";
+        let diagnostics = parse_jenkins_response(response, content);
+
+        let ranges: Vec<(u32, u32, u32)> = diagnostics
+            .iter()
+            .map(|d| {
+                (
+                    d.range.start.line,
+                    d.range.start.character,
+                    d.range.end.character,
+                )
+            })
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![
+                // After a tab, which moves Jenkins' column on to 9
+                (1, 9, 14),
+                (1, 15, 22),
+                // The whole stage header, despite the parentheses in its name
+                (3, 8, 33),
+                (3, 34, 35),
+                (5, 13, 23),
+                // The emoji is two UTF-16 code units
+                (4, 14, 15),
+                (8, 2, 2),
+                // Without a column, the first token on the line
+                (2, 4, 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_classify() {
+        let code = |message: &str| {
+            classify(message).map(|(code, description)| {
+                let NumberOrString::String(code) = code else {
+                    panic!("numeric code for {}", message);
+                };
+                (code, description.href.fragment().unwrap().to_string())
+            })
+        };
+        let expect = |code: &str, anchor: &str| Some((code.to_string(), anchor.to_string()));
+
+        assert_eq!(
+            code("unexpected token: }"),
+            expect("unexpected-token", "declarative-pipeline")
+        );
+        assert_eq!(
+            code("expecting '}', found 'stage'"),
+            expect("unexpected-token", "declarative-pipeline")
+        );
+        assert_eq!(
+            code("No such DSL method 'shh' found among steps [archive, bat, sh]"),
+            expect("unknown-step", "declarative-steps")
+        );
+        assert_eq!(
+            code("Invalid agent type \"vm\" specified. Must be one of [any, docker, label, none]"),
+            expect("invalid-agent-type", "agent")
+        );
+        assert_eq!(
+            code("Missing required section \"stages\""),
+            expect("missing-section", "stages")
+        );
+        assert_eq!(
+            code("Unknown conditional sometimes. Valid conditionals are: branch, expression"),
+            expect("unknown-when-condition", "when")
+        );
+        assert_eq!(
+            code("Invalid option type \"retries\". Valid option types: [retry, timeout]"),
+            expect("invalid-option", "options")
+        );
+        assert_eq!(
+            code("Invalid condition \"sometimes\" - valid conditions are [always]"),
+            expect("invalid-post-condition", "post")
+        );
+        assert_eq!(code("Something else went wrong"), None);
+
+        // Unrecognised output has no code, so reports fall back to the source
+        let diagnostics = parse_jenkins_response(
+            "WorkflowScript: 3: Undefined section \"stuff\" @ line 3, column 5.\n\noops",
+            "",
+        );
+        assert_eq!(
+            diagnostics[0].code,
+            Some(NumberOrString::String("unknown-section".to_string()))
+        );
+        assert_eq!(diagnostics[1].code, None);
+        assert_eq!(diagnostics[1].code_description, None);
     }
 
     #[test]
     fn test_parse_complex_message() {
         let response = "WorkflowScript: 15: expecting '}', found 'stage' @ line 15, column 10.";
-        let diagnostics = parse_jenkins_response(response);
+        let diagnostics = parse_jenkins_response(response, "");
 
         assert_eq!(diagnostics.len(), 1);
         assert_eq!(diagnostics[0].message, "expecting '}', found 'stage'");
@@ -139,7 +766,7 @@ WorkflowScript: 20: Missing closing brace @ line 20, column 3.
     fn test_parse_colon_variant_with_period() {
         // Jenkins can emit with colons after line/column
         let response = "WorkflowScript: 46: unexpected token: } @ line: 46, column: 1.";
-        let diagnostics = parse_jenkins_response(response);
+        let diagnostics = parse_jenkins_response(response, "");
 
         assert_eq!(diagnostics.len(), 1);
         assert_eq!(diagnostics[0].message, "unexpected token: }");
@@ -151,7 +778,7 @@ WorkflowScript: 20: Missing closing brace @ line 20, column 3.
     fn test_parse_colon_variant_no_period() {
         // Jenkins can emit without trailing period
         let response = "WorkflowScript: 46: unexpected token: } @ line: 46, column: 1";
-        let diagnostics = parse_jenkins_response(response);
+        let diagnostics = parse_jenkins_response(response, "");
 
         assert_eq!(diagnostics.len(), 1);
         assert_eq!(diagnostics[0].message, "unexpected token: }");
@@ -0,0 +1,297 @@
+use crate::config::DiscoveryConfig;
+use crate::diagnostics::parse_jenkins_response;
+use crate::jenkins::JenkinsClient;
+use crate::parser::check_jenkinsfile;
+use crate::report::FileReport;
+use crate::types::{LspError, Result, ValidationResult};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::AsyncReadExt;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tower_lsp::lsp_types::Diagnostic;
+
+/// Finds pipeline files under a directory using include/exclude globs
+pub struct Discovery {
+    include: GlobSet,
+    exclude: GlobSet,
+    gitignore: bool,
+}
+
+impl Discovery {
+    /// Compile the configured glob patterns
+    pub fn new(config: &DiscoveryConfig) -> Result<Self> {
+        Ok(Self {
+            include: build_glob_set(&config.include)?,
+            exclude: build_glob_set(&config.exclude)?,
+            gitignore: config.gitignore,
+        })
+    }
+
+    /// Find every pipeline file under `root`, in sorted order
+    ///
+    /// Hidden directories (such as `.git`) are skipped, as are files ignored by
+    /// `.gitignore`, `.ignore` and git's exclude files unless that is turned off.
+    pub fn discover(&self, root: &Path) -> Vec<PathBuf> {
+        let walker = WalkBuilder::new(root)
+            .hidden(true)
+            .parents(self.gitignore)
+            .ignore(self.gitignore)
+            .git_ignore(self.gitignore)
+            .git_global(self.gitignore)
+            .git_exclude(self.gitignore)
+            .require_git(false)
+            .build();
+
+        let mut paths: Vec<PathBuf> = walker
+            .filter_map(|entry| match entry {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    tracing::warn!("Skipping unreadable path: {}", e);
+                    None
+                }
+            })
+            .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+            .map(|entry| entry.into_path())
+            .filter(|path| {
+                path.strip_prefix(root)
+                    .is_ok_and(|relative| self.matches(relative))
+            })
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    /// Whether a path relative to the search root is a pipeline file
+    fn matches(&self, relative: &Path) -> bool {
+        self.include.is_match(relative) && !self.exclude.is_match(relative)
+    }
+}
+
+/// Compile glob patterns in which `*` does not cross directory boundaries
+pub fn build_glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| LspError::Config(format!("Invalid glob pattern '{}': {}", pattern, e)))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| LspError::Config(format!("Invalid glob patterns: {}", e)))
+}
+
+/// Validate files concurrently, with at most `concurrency` in flight at once
+///
+/// Each file is validated remotely by the client `client_for` picks for it, if any.
+/// Reports are returned in the same order as `paths`. A path of `-` reads stdin.
+pub async fn validate_files(
+    paths: &[PathBuf],
+    concurrency: usize,
+    client_for: impl Fn(&Path) -> Option<Arc<JenkinsClient>>,
+) -> Vec<FileReport> {
+    let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut tasks = JoinSet::new();
+
+    for (index, path) in paths.iter().cloned().enumerate() {
+        let jenkins_client = client_for(&path);
+        let semaphore = semaphore.clone();
+        tasks.spawn(async move {
+            let _permit = semaphore
+                .acquire_owned()
+                .await
+                .expect("semaphore is never closed");
+            (index, validate_file(jenkins_client.as_deref(), &path).await)
+        });
+    }
+
+    let mut reports: Vec<Option<FileReport>> = vec![None; paths.len()];
+    while let Some(joined) = tasks.join_next().await {
+        let (index, report) = joined.expect("validation task panicked");
+        reports[index] = Some(report);
+    }
+    reports.into_iter().flatten().collect()
+}
+
+/// Run local checks and (if configured) remote validation on a single file
+async fn validate_file(jenkins_client: Option<&JenkinsClient>, path: &Path) -> FileReport {
+    let name = display_name(path);
+    let content = match read_input(path).await {
+        Ok(content) => content,
+        Err(e) => return FileReport::new(name, Err(e)),
+    };
+
+    let (diagnostics, error) = validate_content(jenkins_client, &content).await;
+    FileReport {
+        path: name,
+        diagnostics,
+        failure: error.map(|e| e.to_string()),
+    }
+}
+
+/// Run local checks and (if configured) remote validation on some content
+///
+/// The local diagnostics are kept even if remote validation fails, in which case
+/// the error is returned alongside them.
+pub async fn validate_content(
+    jenkins_client: Option<&JenkinsClient>,
+    content: &str,
+) -> (Vec<Diagnostic>, Option<LspError>) {
+    let mut diagnostics = check_jenkinsfile(content);
+    let Some(jenkins_client) = jenkins_client else {
+        return (diagnostics, None);
+    };
+
+    match jenkins_client.validate(content).await {
+        Ok(ValidationResult::Success) => (diagnostics, None),
+        Ok(ValidationResult::Error(response)) => {
+            diagnostics.extend(parse_jenkins_response(&response, content));
+            (diagnostics, None)
+        }
+        Err(e) => (diagnostics, Some(e)),
+    }
+}
+
+/// Read a file, or stdin if the path is `-`
+async fn read_input(path: &Path) -> Result<String> {
+    if path == Path::new("-") {
+        let mut content = String::new();
+        tokio::io::stdin().read_to_string(&mut content).await?;
+        Ok(content)
+    } else {
+        Ok(tokio::fs::read_to_string(path).await?)
+    }
+}
+
+/// Name to show for a path in reports
+pub fn display_name(path: &Path) -> String {
+    if path == Path::new("-") {
+        "<stdin>".to_string()
+    } else {
+        path.display().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn touch(root: &Path, relative: &str) {
+        let path = root.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "node {}\n").unwrap();
+    }
+
+    fn discovered(discovery: &Discovery, root: &Path) -> Vec<String> {
+        discovery
+            .discover(root)
+            .iter()
+            .map(|p| {
+                p.strip_prefix(root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect()
+    }
+
+    fn tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        touch(root, "Jenkinsfile");
+        touch(root, "services/api/Jenkinsfile");
+        touch(root, "services/api/release.jenkinsfile");
+        touch(root, "ci/deploy.groovy");
+        touch(root, "ci/lib/helper.groovy");
+        touch(root, "src/Main.groovy");
+        touch(root, "build/Jenkinsfile");
+        touch(root, ".git/Jenkinsfile");
+        fs::write(root.join(".gitignore"), "build/\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_default_discovery() {
+        let dir = tree();
+        let discovery = Discovery::new(&DiscoveryConfig::default()).unwrap();
+        assert_eq!(
+            discovered(&discovery, dir.path()),
+            vec![
+                "Jenkinsfile",
+                "ci/deploy.groovy",
+                "services/api/Jenkinsfile",
+                "services/api/release.jenkinsfile",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_exclude_and_gitignore_settings() {
+        let dir = tree();
+        let discovery = Discovery::new(&DiscoveryConfig {
+            exclude: vec!["services/**".to_string()],
+            gitignore: false,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            discovered(&discovery, dir.path()),
+            vec!["Jenkinsfile", "build/Jenkinsfile", "ci/deploy.groovy"]
+        );
+    }
+
+    #[test]
+    fn test_custom_include() {
+        let dir = tree();
+        let discovery = Discovery::new(&DiscoveryConfig {
+            include: vec!["ci/**/*.groovy".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            discovered(&discovery, dir.path()),
+            vec!["ci/deploy.groovy", "ci/lib/helper.groovy"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_glob() {
+        let result = Discovery::new(&DiscoveryConfig {
+            include: vec!["[".to_string()],
+            ..Default::default()
+        });
+        assert!(matches!(result, Err(LspError::Config(_))));
+    }
+
+    #[test]
+    fn test_display_name_for_stdin() {
+        assert_eq!(display_name(Path::new("-")), "<stdin>");
+        assert_eq!(display_name(Path::new("Jenkinsfile")), "Jenkinsfile");
+    }
+
+    #[tokio::test]
+    async fn test_validate_files_preserves_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let good = dir.path().join("good");
+        let bad = dir.path().join("bad");
+        let missing = dir.path().join("missing");
+        fs::write(&good, "node {}\n").unwrap();
+        fs::write(&bad, "node {\n").unwrap();
+
+        let paths = vec![bad.clone(), missing, good.clone()];
+        let reports = validate_files(&paths, 2, |_| None).await;
+
+        assert_eq!(reports.len(), 3);
+        assert_eq!(reports[0].path, bad.display().to_string());
+        assert!(reports[0].has_errors());
+        assert!(reports[1].failure.is_some());
+        assert_eq!(reports[2].path, good.display().to_string());
+        assert!(reports[2].diagnostics.is_empty());
+        assert!(reports[2].failure.is_none());
+    }
+}
@@ -0,0 +1,190 @@
+use tower_lsp::lsp_types::{Position, TextDocumentContentChangeEvent};
+
+/// An open text document, indexed by line for applying incremental edits
+///
+/// LSP positions count characters in UTF-16 code units, while the text is stored
+/// as UTF-8, so every position has to be converted through the line index.
+#[derive(Debug, Clone)]
+pub struct Document {
+    text: String,
+    version: i32,
+    /// Byte offset at which each line starts (always contains at least one entry)
+    line_starts: Vec<usize>,
+}
+
+impl Document {
+    /// Create a document from its full text
+    pub fn new(text: String, version: i32) -> Self {
+        let line_starts = line_starts(&text);
+        Self {
+            text,
+            version,
+            line_starts,
+        }
+    }
+
+    /// The current document text
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The version most recently reported by the client
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+
+    /// Record the version the client assigned after a batch of changes
+    pub fn set_version(&mut self, version: i32) {
+        self.version = version;
+    }
+
+    /// Apply a single content change
+    ///
+    /// Changes without a range replace the whole document (as in FULL sync);
+    /// ranged changes replace just that span.
+    pub fn apply_change(&mut self, change: TextDocumentContentChangeEvent) {
+        match change.range {
+            Some(range) => {
+                let start = self.offset_at(range.start);
+                let end = self.offset_at(range.end).max(start);
+                self.text.replace_range(start..end, &change.text);
+            }
+            None => self.text = change.text,
+        }
+        self.line_starts = line_starts(&self.text);
+    }
+
+    /// Convert an LSP position to a byte offset into the text
+    ///
+    /// Positions past the end of a line clamp to the end of that line, and
+    /// positions past the last line clamp to the end of the document.
+    pub fn offset_at(&self, position: Position) -> usize {
+        let Some(&line_start) = self.line_starts.get(position.line as usize) else {
+            return self.text.len();
+        };
+        let line_end = self
+            .line_starts
+            .get(position.line as usize + 1)
+            .copied()
+            .unwrap_or(self.text.len());
+        let line = self.text[line_start..line_end].trim_end_matches(['\n', '\r']);
+
+        let mut units = 0;
+        for (offset, ch) in line.char_indices() {
+            if units >= position.character as usize {
+                return line_start + offset;
+            }
+            units += ch.len_utf16();
+        }
+        line_start + line.len()
+    }
+}
+
+/// Compute the byte offset at which each line starts
+///
+/// Lines are terminated by `\n`, `\r\n` or a lone `\r`, as in the LSP specification.
+fn line_starts(text: &str) -> Vec<usize> {
+    let bytes = text.as_bytes();
+    let mut starts = vec![0];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\n' => starts.push(i + 1),
+            b'\r' if bytes.get(i + 1) == Some(&b'\n') => {
+                starts.push(i + 2);
+                i += 1;
+            }
+            b'\r' => starts.push(i + 1),
+            _ => {}
+        }
+        i += 1;
+    }
+    starts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp::lsp_types::Range;
+
+    fn edit(start: (u32, u32), end: (u32, u32), text: &str) -> TextDocumentContentChangeEvent {
+        TextDocumentContentChangeEvent {
+            range: Some(Range {
+                start: Position::new(start.0, start.1),
+                end: Position::new(end.0, end.1),
+            }),
+            range_length: None,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_insert_within_line() {
+        let mut doc = Document::new("pipeline {\n  agent any\n}\n".to_string(), 1);
+        doc.apply_change(edit((1, 8), (1, 11), "none"));
+        assert_eq!(doc.text(), "pipeline {\n  agent none\n}\n");
+    }
+
+    #[test]
+    fn test_multiline_replace_and_delete() {
+        let mut doc = Document::new("a\nb\nc\nd".to_string(), 1);
+        doc.apply_change(edit((1, 0), (2, 1), "x\ny\nz"));
+        assert_eq!(doc.text(), "a\nx\ny\nz\nd");
+
+        // Line index must reflect the previous edit
+        doc.apply_change(edit((3, 0), (4, 0), ""));
+        assert_eq!(doc.text(), "a\nx\ny\nd");
+    }
+
+    #[test]
+    fn test_sequential_edits_in_one_batch() {
+        let mut doc = Document::new("stage('Build') {\n}".to_string(), 1);
+        doc.apply_change(edit((0, 16), (0, 16), "\n    steps {}"));
+        doc.apply_change(edit((1, 11), (1, 11), " sh 'make' "));
+        doc.set_version(3);
+        assert_eq!(doc.text(), "stage('Build') {\n    steps { sh 'make' }\n}");
+        assert_eq!(doc.version(), 3);
+    }
+
+    #[test]
+    fn test_utf16_positions() {
+        // "é" is one UTF-16 unit (two UTF-8 bytes); "😀" is two UTF-16 units (four bytes)
+        let mut doc = Document::new("echo 'é😀x'".to_string(), 1);
+        doc.apply_change(edit((0, 9), (0, 10), "y"));
+        assert_eq!(doc.text(), "echo 'é😀y'");
+        doc.apply_change(edit((0, 7), (0, 9), ""));
+        assert_eq!(doc.text(), "echo 'éy'");
+    }
+
+    #[test]
+    fn test_crlf_and_lone_cr_line_endings() {
+        let mut doc = Document::new("a\r\nb\rc".to_string(), 1);
+        doc.apply_change(edit((1, 0), (1, 1), "B"));
+        doc.apply_change(edit((2, 0), (2, 1), "C"));
+        assert_eq!(doc.text(), "a\r\nB\rC");
+
+        // Past the end of a line clamps before its terminator
+        doc.apply_change(edit((0, 50), (0, 50), "!"));
+        assert_eq!(doc.text(), "a!\r\nB\rC");
+    }
+
+    #[test]
+    fn test_out_of_range_position_clamps_to_end() {
+        let mut doc = Document::new("one\ntwo".to_string(), 1);
+        doc.apply_change(edit((10, 0), (10, 0), "\nthree"));
+        assert_eq!(doc.text(), "one\ntwo\nthree");
+    }
+
+    #[test]
+    fn test_full_replacement() {
+        let mut doc = Document::new("old".to_string(), 1);
+        doc.apply_change(TextDocumentContentChangeEvent {
+            range: None,
+            range_length: None,
+            text: "new\ntext".to_string(),
+        });
+        assert_eq!(doc.text(), "new\ntext");
+        doc.apply_change(edit((1, 0), (1, 4), "body"));
+        assert_eq!(doc.text(), "new\nbody");
+    }
+}
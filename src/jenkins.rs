@@ -1,23 +1,153 @@
-use crate::config::Config;
+use crate::cache::ValidationCache;
+use crate::config::{AuthMode, Config};
+use crate::credentials::expand_home;
+use crate::diagnostics::excerpt;
+use crate::retry::{CircuitBreaker, backoff_delay};
 use crate::types::{Crumb, LspError, Result, ValidationResult};
-use reqwest::{Client, multipart};
+use reqwest::{
+    Certificate, Client, Identity, NoProxy, Proxy, RequestBuilder, Response, StatusCode, multipart,
+};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
 /// Jenkins API client for validating Jenkinsfiles
 pub struct JenkinsClient {
     config: Config,
     client: Client,
+    /// Results for content already validated, if caching is enabled
+    cache: Option<Arc<ValidationCache>>,
+    /// Version of Jenkins, once a response has told us
+    jenkins_version: RwLock<Option<String>>,
+    /// Crumb for the current web session, reused until Jenkins rejects it
+    crumb: tokio::sync::Mutex<Option<Crumb>>,
+    /// Stops requests while Jenkins keeps failing
+    breaker: CircuitBreaker,
 }
 
 impl JenkinsClient {
     /// Create a new Jenkins client with the given configuration
-    pub fn new(config: Config) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
-            .danger_accept_invalid_certs(config.insecure)
-            .build()?;
+    ///
+    /// Clients for different instances can share a validation cache.
+    pub fn new(config: Config, cache: Option<Arc<ValidationCache>>) -> Result<Self> {
+        let client = build_client(&config)?;
+        let breaker = CircuitBreaker::new(&config.retry);
+
+        Ok(Self {
+            config,
+            client,
+            cache,
+            jenkins_version: RwLock::new(None),
+            crumb: tokio::sync::Mutex::new(None),
+            breaker,
+        })
+    }
+
+    /// URL of the Jenkins instance this client talks to
+    pub fn url(&self) -> &str {
+        &self.config.jenkins_url
+    }
+
+    /// Whether recent requests have failed often enough to treat Jenkins as down
+    pub fn is_degraded(&self) -> bool {
+        self.breaker.is_open()
+    }
+
+    /// Send a request, retrying transient failures with jittered exponential backoff
+    ///
+    /// Connection failures, timeouts and 502/503/504 responses are retried, since
+    /// they usually come from Jenkins restarting or the proxy in front of it. Each
+    /// request's final outcome is recorded by the circuit breaker, and no request is
+    /// sent at all while the circuit is open.
+    async fn send(&self, request: impl Fn() -> RequestBuilder) -> Result<Response> {
+        self.breaker.check()?;
+
+        let mut attempt = 0;
+        loop {
+            let outcome = request().send().await;
+            let transient = match &outcome {
+                Ok(response) => is_transient_status(response.status()),
+                Err(e) => e.is_connect() || e.is_timeout(),
+            };
+
+            if transient && attempt < self.config.retry.max_retries {
+                let delay = backoff_delay(&self.config.retry, attempt);
+                tracing::debug!(
+                    "Request to Jenkins failed ({}), retrying in {:?}",
+                    match &outcome {
+                        Ok(response) => response.status().to_string(),
+                        Err(e) => e.to_string(),
+                    },
+                    delay
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+                continue;
+            }
+
+            if transient {
+                self.breaker.record_failure();
+            }
+            let response = outcome?;
+            if transient {
+                return Err(LspError::Unavailable(format!(
+                    "{} from {}",
+                    response.status(),
+                    response.url()
+                )));
+            }
+            self.breaker.record_success();
+            self.record_version(&response);
+            return Ok(response);
+        }
+    }
+
+    /// Remember the Jenkins version from a response's `X-Jenkins` header
+    fn record_version(&self, response: &Response) {
+        if let Some(version) = response
+            .headers()
+            .get("X-Jenkins")
+            .and_then(|v| v.to_str().ok())
+        {
+            let mut current = self.jenkins_version.write().unwrap();
+            if current.as_deref() != Some(version) {
+                tracing::debug!("Jenkins version: {}", version);
+                *current = Some(version.to_string());
+            }
+        }
+    }
 
-        Ok(Self { config, client })
+    /// Add the configured credentials to a request
+    fn authenticate(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.config.auth {
+            AuthMode::Basic => {
+                request.basic_auth(&self.config.username, Some(&self.config.api_token))
+            }
+            AuthMode::Bearer => request.bearer_auth(&self.config.api_token),
+            AuthMode::Header(name) => request.header(name, &self.config.api_token),
+            AuthMode::None => request,
+        }
+    }
+
+    /// Check whether Jenkins can be reached at all
+    ///
+    /// Any HTTP response counts, even an error status, except the 502/503/504 that a
+    /// proxy in front of Jenkins gives while Jenkins itself is down.
+    pub async fn ping(&self) -> Result<()> {
+        let response = self
+            .authenticate(self.client.get(&self.config.jenkins_url))
+            .send()
+            .await?;
+        if is_transient_status(response.status()) {
+            return Err(LspError::Unavailable(format!(
+                "{} from {}",
+                response.status(),
+                response.url()
+            )));
+        }
+        self.breaker.record_success();
+        self.record_version(&response);
+        Ok(())
     }
 
     /// Fetch CSRF crumb from Jenkins
@@ -28,20 +158,23 @@ impl JenkinsClient {
         let url = format!("{}/crumbIssuer/api/json", self.config.jenkins_url);
 
         let response = self
-            .client
-            .get(&url)
-            .basic_auth(&self.config.username, Some(&self.config.api_token))
-            .send()
+            .send(|| self.authenticate(self.client.get(&url)))
             .await?;
 
         if response.status().is_success() {
-            let crumb: Crumb = response.json().await?;
-            Ok(crumb)
-        } else if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+            let body = checked_body(response).await?;
+            serde_json::from_str(&body).map_err(|e| {
+                LspError::JenkinsApi(format!(
+                    "Unexpected crumb response ({}): {}",
+                    e,
+                    excerpt(&body)
+                ))
+            })
+        } else if response.status() == StatusCode::UNAUTHORIZED {
             Err(LspError::Auth(
                 "Authentication failed. Check your credentials.".to_string(),
             ))
-        } else if response.status() == reqwest::StatusCode::NOT_FOUND {
+        } else if response.status() == StatusCode::NOT_FOUND {
             Err(LspError::JenkinsApi(
                 "Crumb issuer endpoint not found. CSRF protection may be disabled.".to_string(),
             ))
@@ -50,7 +183,8 @@ impl JenkinsClient {
             let body = response.text().await.unwrap_or_default();
             Err(LspError::JenkinsApi(format!(
                 "Failed to get crumb: {} - {}",
-                status, body
+                status,
+                excerpt(&body)
             )))
         }
     }
@@ -64,26 +198,36 @@ impl JenkinsClient {
             self.config.jenkins_url
         );
 
-        // Create multipart form with Jenkinsfile content
-        let form = multipart::Form::new().text("jenkinsfile", content.to_string());
-
         let response = self
-            .client
-            .post(&url)
-            .basic_auth(&self.config.username, Some(&self.config.api_token))
-            .header(&crumb.crumb_request_field, &crumb.crumb)
-            .multipart(form)
-            .send()
+            .send(|| {
+                // Create multipart form with Jenkinsfile content (afresh for each attempt,
+                // since a sent form can't be reused)
+                let form = multipart::Form::new().text("jenkinsfile", content.to_string());
+                self.authenticate(self.client.post(&url))
+                    .header(&crumb.crumb_request_field, &crumb.crumb)
+                    .multipart(form)
+            })
             .await?;
 
         if response.status().is_success() {
-            let body = response.text().await?;
-            Ok(body)
-        } else if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+            checked_body(response).await
+        } else if response.status() == StatusCode::UNAUTHORIZED {
             Err(LspError::Auth(
                 "Authentication failed during validation.".to_string(),
             ))
-        } else if response.status() == reqwest::StatusCode::NOT_FOUND {
+        } else if response.status() == StatusCode::FORBIDDEN {
+            let body = response.text().await.unwrap_or_default();
+            if body.contains("No valid crumb") {
+                Err(LspError::JenkinsApi(
+                    "No valid crumb was included in the request.".to_string(),
+                ))
+            } else {
+                Err(LspError::JenkinsApi(format!(
+                    "Validation request forbidden: {}",
+                    excerpt(&body)
+                )))
+            }
+        } else if response.status() == StatusCode::NOT_FOUND {
             Err(LspError::JenkinsApi(
                 "Validation endpoint not found. Ensure pipeline-model-definition plugin is installed.".to_string(),
             ))
@@ -92,7 +236,8 @@ impl JenkinsClient {
             let body = response.text().await.unwrap_or_default();
             Err(LspError::JenkinsApi(format!(
                 "Validation request failed: {} - {}",
-                status, body
+                status,
+                excerpt(&body)
             )))
         }
     }
@@ -100,7 +245,35 @@ impl JenkinsClient {
     /// Validate a Jenkinsfile and return a ValidationResult
     ///
     /// This is a convenience method that combines getting the crumb and validating.
+    /// Content validated recently is answered from the cache without asking Jenkins.
     pub async fn validate(&self, content: &str) -> Result<ValidationResult> {
+        let Some(cache) = &self.cache else {
+            return self.validate_uncached(content).await;
+        };
+
+        // The version is the one the last response from Jenkins reported
+        let key = ValidationCache::key(&self.config.jenkins_url, content);
+        let jenkins_version = self.jenkins_version.read().unwrap().clone();
+        if let Some(result) = cache.get(&key, jenkins_version.as_deref()) {
+            tracing::debug!("Using cached validation result");
+            return Ok(result);
+        }
+
+        let result = self.validate_uncached(content).await?;
+        let jenkins_version = self.jenkins_version.read().unwrap().clone();
+        cache.insert(key, jenkins_version, result.clone()).await;
+        Ok(result)
+    }
+
+    /// Get the crumb for the current session, fetching one if there isn't one yet
+    ///
+    /// Concurrent callers wait for a single fetch rather than each making their own.
+    async fn session_crumb(&self) -> Result<Crumb> {
+        let mut cached = self.crumb.lock().await;
+        if let Some(crumb) = cached.as_ref() {
+            return Ok(crumb.clone());
+        }
+
         // Try to get crumb, but continue if it fails (some Jenkins instances don't require it)
         let crumb = match self.get_crumb().await {
             Ok(crumb) => crumb,
@@ -115,8 +288,31 @@ impl JenkinsClient {
             // Propagate all other errors (Auth, Network, other API errors like 500)
             Err(e) => return Err(e),
         };
+        *cached = Some(crumb.clone());
+        Ok(crumb)
+    }
 
-        let response = self.validate_jenkinsfile(content, &crumb).await?;
+    /// Drop a crumb Jenkins rejected, unless another request already replaced it
+    async fn forget_crumb(&self, rejected: &Crumb) {
+        let mut cached = self.crumb.lock().await;
+        if cached.as_ref() == Some(rejected) {
+            *cached = None;
+        }
+    }
+
+    /// Validate a Jenkinsfile by asking Jenkins, bypassing the cache
+    async fn validate_uncached(&self, content: &str) -> Result<ValidationResult> {
+        let crumb = self.session_crumb().await?;
+        let response = match self.validate_jenkinsfile(content, &crumb).await {
+            // The session expired (or Jenkins restarted), so get a new crumb and try once more
+            Err(LspError::JenkinsApi(ref msg)) if msg.contains("No valid crumb") => {
+                tracing::debug!("Crumb rejected, fetching a new one");
+                self.forget_crumb(&crumb).await;
+                let crumb = self.session_crumb().await?;
+                self.validate_jenkinsfile(content, &crumb).await?
+            }
+            result => result?,
+        };
 
         if response.contains("Jenkinsfile successfully validated.") {
             Ok(ValidationResult::Success)
@@ -126,9 +322,380 @@ impl JenkinsClient {
     }
 }
 
+/// Read the body of a successful response, which should come from Jenkins' API
+///
+/// An HTML page instead means the request went somewhere else: usually a login page
+/// that an SSO proxy redirected it to, since the credentials weren't accepted. This is
+/// decided from the body, since Jenkins labels its plain-text answers `text/html` too.
+async fn checked_body(response: Response) -> Result<String> {
+    let url = response.url().clone();
+    let labelled_html = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("text/html"));
+    let body = response.text().await?;
+
+    let lowercase = body.to_ascii_lowercase();
+    let start = lowercase.trim_start();
+    // A page labelled HTML may start with a comment or whitespace before its markup
+    let is_html = start.starts_with("<!doctype html")
+        || start.starts_with("<html")
+        || (labelled_html && start.starts_with('<') && lowercase.contains("<html"));
+    if !is_html {
+        return Ok(body);
+    }
+
+    let is_login = is_login_path(url.path())
+        || lowercase.contains("type=\"password\"")
+        || lowercase.contains("type='password'");
+    if is_login {
+        Err(LspError::Auth(format!(
+            "Jenkins responded with a login page ({}). Check your credentials and auth mode.",
+            url
+        )))
+    } else {
+        Err(LspError::JenkinsApi(format!(
+            "Expected a response from Jenkins' API, but got an HTML page from {}: {}",
+            url,
+            excerpt(&body)
+        )))
+    }
+}
+
+/// Whether a URL path is a login page's, judging by whole segments like `/login`
+fn is_login_path(path: &str) -> bool {
+    path.split('/').any(|segment| {
+        matches!(
+            segment.to_ascii_lowercase().as_str(),
+            "login" | "signin" | "sign-in" | "sso" | "j_security_check"
+        )
+    })
+}
+
+/// Whether a response status means Jenkins (or its proxy) is temporarily unavailable
+fn is_transient_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Create the HTTP client, with the configured proxy and TLS settings
+fn build_client(config: &Config) -> Result<Client> {
+    let mut builder = Client::builder()
+        .timeout(Duration::from_secs(30))
+        .danger_accept_invalid_certs(config.insecure)
+        // Crumbs are only valid for the web session they were issued in
+        .cookie_store(true);
+
+    if let Some(url) = &config.proxy.url {
+        let proxy = Proxy::all(url)
+            .map_err(|e| LspError::Config(format!("Invalid proxy url {}: {}", url, e)))?
+            .no_proxy(NoProxy::from_string(&config.proxy.no_proxy.join(",")));
+        builder = builder.proxy(proxy);
+    }
+
+    for path in &config.tls.ca_certs {
+        let certificates = Certificate::from_pem_bundle(&read_pem(path)?)
+            .map_err(|e| pem_error(&format!("CA certificates in {}", path.display()), e))?;
+        if certificates.is_empty() {
+            return Err(LspError::Config(format!(
+                "No certificates found in {}",
+                path.display()
+            )));
+        }
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    if let (Some(cert), Some(key)) = (&config.tls.client_cert, &config.tls.client_key) {
+        let identity =
+            Identity::from_pkcs8_pem(&read_pem(cert)?, &read_pem(key)?).map_err(|e| {
+                let files = format!("{} and {}", cert.display(), key.display());
+                pem_error(&format!("client certificate and key in {}", files), e)
+            })?;
+        builder = builder.identity(identity);
+    }
+
+    Ok(builder.build()?)
+}
+
+fn read_pem(path: &Path) -> Result<Vec<u8>> {
+    let path = expand_home(path);
+    std::fs::read(&path)
+        .map_err(|e| LspError::Config(format!("Failed to read {}: {}", path.display(), e)))
+}
+
+fn pem_error(what: &str, e: reqwest::Error) -> LspError {
+    // reqwest only says "builder error"; the TLS library's error says what's wrong
+    let reason = std::error::Error::source(&e).map_or_else(|| e.to_string(), |s| s.to_string());
+    LspError::Config(format!("Invalid {}: {}", what, reason))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::{CacheConfig, RetryConfig};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// Serve canned `(status line and headers, body)` replies in order, one per
+    /// connection, and return the server's URL and the (lowercased) requests it got
+    async fn fake_jenkins(
+        replies: Vec<(&'static str, &'static str)>,
+    ) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for (head, body) in replies {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut stream = BufReader::new(stream);
+                let mut request = String::new();
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    stream.read_line(&mut line).await.unwrap();
+                    let line = line.to_lowercase();
+                    if let Some(length) = line.strip_prefix("content-length:") {
+                        content_length = length.trim().parse().unwrap();
+                    }
+                    if line == "\r\n" {
+                        break;
+                    }
+                    request.push_str(&line);
+                }
+                let mut request_body = vec![0; content_length];
+                stream.read_exact(&mut request_body).await.unwrap();
+                requests.push(request);
+
+                let reply = format!(
+                    "{}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    head,
+                    body.len(),
+                    body
+                );
+                stream.get_mut().write_all(reply.as_bytes()).await.unwrap();
+            }
+            requests
+        });
+        (url, server)
+    }
+
+    fn client_for(url: String) -> JenkinsClient {
+        let config = Config {
+            jenkins_url: url,
+            username: "test".to_string(),
+            api_token: "token".to_string(),
+            retry: RetryConfig {
+                backoff_ms: 1,
+                max_backoff_ms: 1,
+                failure_threshold: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        JenkinsClient::new(config, None).unwrap()
+    }
+
+    const CRUMB: (&str, &str) = (
+        "HTTP/1.1 200 OK",
+        r#"{"crumb":"c1","crumbRequestField":"Jenkins-Crumb"}"#,
+    );
+    const BAD_GATEWAY: (&str, &str) = ("HTTP/1.1 502 Bad Gateway", "");
+
+    const VALID: &str = "Jenkinsfile successfully validated.";
+
+    #[tokio::test]
+    async fn test_crumb_and_session_are_reused() {
+        let (url, server) = fake_jenkins(vec![
+            (
+                "HTTP/1.1 200 OK\r\nSet-Cookie: JSESSIONID=s1; Path=/",
+                r#"{"crumb":"c1","crumbRequestField":"Jenkins-Crumb"}"#,
+            ),
+            ("HTTP/1.1 200 OK", VALID),
+            ("HTTP/1.1 200 OK", VALID),
+        ])
+        .await;
+        let client = client_for(url);
+
+        assert_eq!(
+            client.validate("node {}").await.unwrap(),
+            ValidationResult::Success
+        );
+        assert_eq!(
+            client.validate("node { }").await.unwrap(),
+            ValidationResult::Success
+        );
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("get /crumbissuer/api/json"));
+        for post in &requests[1..] {
+            assert!(post.starts_with("post /pipeline-model-converter/validate"));
+            assert!(post.contains("jenkins-crumb: c1"));
+            assert!(post.contains("cookie: jsessionid=s1"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cached_results_need_the_running_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_config = CacheConfig {
+            dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let (url, server) = fake_jenkins(vec![
+            CRUMB,
+            ("HTTP/1.1 200 OK\r\nX-Jenkins: 2.401", VALID),
+            ("HTTP/1.1 200 OK\r\nX-Jenkins: 2.402", VALID),
+            ("HTTP/1.1 200 OK\r\nX-Jenkins: 2.402", VALID),
+        ])
+        .await;
+
+        // A result persisted before Jenkins was upgraded
+        ValidationCache::new(&cache_config)
+            .insert(
+                ValidationCache::key(&url, "node {}"),
+                Some("2.400".to_string()),
+                ValidationResult::Error("stale".to_string()),
+            )
+            .await;
+
+        let config = Config {
+            jenkins_url: url,
+            username: "test".to_string(),
+            api_token: "token".to_string(),
+            ..Default::default()
+        };
+        let cache = Arc::new(ValidationCache::new(&cache_config));
+        let client = JenkinsClient::new(config, Some(cache)).unwrap();
+        assert_eq!(
+            client.validate("node {}").await.unwrap(),
+            ValidationResult::Success
+        );
+        // The fresh result is reused without asking Jenkins again
+        assert_eq!(
+            client.validate("node {}").await.unwrap(),
+            ValidationResult::Success
+        );
+        // Until a response shows that Jenkins was upgraded since
+        client.validate("node { }").await.unwrap();
+        client.validate("node {}").await.unwrap();
+        assert_eq!(server.await.unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_transient_failures_are_retried() {
+        let (url, server) = fake_jenkins(vec![
+            CRUMB,
+            BAD_GATEWAY,
+            BAD_GATEWAY,
+            ("HTTP/1.1 200 OK", VALID),
+        ])
+        .await;
+        let client = client_for(url);
+
+        assert_eq!(
+            client.validate("node {}").await.unwrap(),
+            ValidationResult::Success
+        );
+        assert_eq!(server.await.unwrap().len(), 4);
+        assert!(!client.is_degraded());
+    }
+
+    #[tokio::test]
+    async fn test_circuit_opens_after_repeated_failures() {
+        // Each validation makes three attempts (two retries), and the second
+        // failed validation opens the circuit
+        let (url, server) = fake_jenkins(vec![
+            CRUMB,
+            BAD_GATEWAY,
+            BAD_GATEWAY,
+            BAD_GATEWAY,
+            BAD_GATEWAY,
+            BAD_GATEWAY,
+            BAD_GATEWAY,
+        ])
+        .await;
+        let client = client_for(url);
+
+        for _ in 0..2 {
+            assert!(matches!(
+                client.validate("node {}").await,
+                Err(LspError::Unavailable(_))
+            ));
+        }
+        assert!(client.is_degraded());
+        assert_eq!(server.await.unwrap().len(), 7);
+
+        // Nothing else is sent while the circuit is open
+        assert!(matches!(
+            client.validate("node {}").await,
+            Err(LspError::Unavailable(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_ping_needs_jenkins_itself() {
+        let (url, server) = fake_jenkins(vec![
+            CRUMB,
+            BAD_GATEWAY,
+            BAD_GATEWAY,
+            BAD_GATEWAY,
+            BAD_GATEWAY,
+            BAD_GATEWAY,
+            BAD_GATEWAY,
+            ("HTTP/1.1 503 Service Unavailable", ""),
+            ("HTTP/1.1 403 Forbidden", ""),
+        ])
+        .await;
+        let client = client_for(url);
+        for _ in 0..2 {
+            assert!(client.validate("node {}").await.is_err());
+        }
+        assert!(client.is_degraded());
+
+        // The proxy answering for a Jenkins that is still down doesn't count
+        assert!(matches!(client.ping().await, Err(LspError::Unavailable(_))));
+        assert!(client.is_degraded());
+        assert!(client.ping().await.is_ok());
+        assert!(!client.is_degraded());
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rejected_crumb_is_refreshed() {
+        let (url, server) = fake_jenkins(vec![
+            (
+                "HTTP/1.1 200 OK\r\nSet-Cookie: JSESSIONID=s1; Path=/",
+                r#"{"crumb":"c1","crumbRequestField":"Jenkins-Crumb"}"#,
+            ),
+            (
+                "HTTP/1.1 403 Forbidden",
+                "No valid crumb was included in the request",
+            ),
+            (
+                "HTTP/1.1 200 OK\r\nSet-Cookie: JSESSIONID=s2; Path=/",
+                r#"{"crumb":"c2","crumbRequestField":"Jenkins-Crumb"}"#,
+            ),
+            ("HTTP/1.1 200 OK", VALID),
+        ])
+        .await;
+        let client = client_for(url);
+
+        assert_eq!(
+            client.validate("node {}").await.unwrap(),
+            ValidationResult::Success
+        );
+
+        let requests = server.await.unwrap();
+        assert_eq!(requests.len(), 4);
+        assert!(requests[2].starts_with("get /crumbissuer/api/json"));
+        assert!(requests[3].contains("jenkins-crumb: c2"));
+        assert!(requests[3].contains("cookie: jsessionid=s2"));
+    }
 
     #[test]
     fn test_jenkins_client_creation() {
@@ -136,13 +703,117 @@ mod tests {
             jenkins_url: "https://jenkins.example.com".to_string(),
             username: "test".to_string(),
             api_token: "token".to_string(),
-            insecure: false,
+            ..Default::default()
         };
 
-        let client = JenkinsClient::new(config);
+        let client = JenkinsClient::new(config, None);
         assert!(client.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_requests_go_through_proxy() {
+        let (proxy_url, server) = fake_jenkins(vec![CRUMB]).await;
+        let mut config = client_for("http://jenkins.internal".to_string()).config;
+        config.proxy.url = Some(proxy_url);
+        let client = JenkinsClient::new(config, None).unwrap();
+
+        assert_eq!(client.get_crumb().await.unwrap().crumb, "c1");
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("get http://jenkins.internal/crumbissuer/api/json"));
+    }
+
+    #[tokio::test]
+    async fn test_auth_modes() {
+        for (auth, expected) in [
+            (AuthMode::Basic, Some("authorization: basic ")),
+            (AuthMode::Bearer, Some("authorization: bearer token")),
+            (
+                AuthMode::Header("X-Auth-Token".to_string()),
+                Some("x-auth-token: token"),
+            ),
+            (AuthMode::None, None),
+        ] {
+            let (url, server) = fake_jenkins(vec![CRUMB]).await;
+            let mut config = client_for(url).config;
+            config.auth = auth;
+            JenkinsClient::new(config, None)
+                .unwrap()
+                .get_crumb()
+                .await
+                .unwrap();
+
+            let request = &server.await.unwrap()[0];
+            match expected {
+                Some(header) => assert!(request.contains(header), "{}", request),
+                None => assert!(!request.contains("authorization"), "{}", request),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_html_responses_are_errors() {
+        let login = (
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html;charset=utf-8",
+            r#"<html><body><form action="/j_security_check"><input type="password" name="j_password"></form></body></html>"#,
+        );
+        let (url, _server) = fake_jenkins(vec![CRUMB, login]).await;
+        let result = client_for(url).validate("node {}").await;
+        assert!(matches!(result, Err(LspError::Auth(_))), "{:?}", result);
+
+        let maintenance = (
+            "HTTP/1.1 200 OK",
+            "<!DOCTYPE html>\n<html><title>Down for maintenance</title></html>",
+        );
+        let (url, _server) = fake_jenkins(vec![CRUMB, maintenance]).await;
+        match client_for(url).validate("node {}").await {
+            Err(LspError::JenkinsApi(message)) => {
+                assert!(message.ends_with(": Down for maintenance"), "{}", message)
+            }
+            result => panic!("{:?}", result),
+        }
+
+        let labelled_html = (
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html;charset=utf-8",
+            "Jenkinsfile successfully validated.\n",
+        );
+        let (url, _server) = fake_jenkins(vec![CRUMB, labelled_html]).await;
+        let result = client_for(url).validate("node {}").await;
+        assert!(
+            matches!(result, Ok(ValidationResult::Success)),
+            "{:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_is_login_path() {
+        assert!(is_login_path("/login"));
+        assert!(is_login_path("/sso/redirect"));
+        assert!(is_login_path("/j_security_check"));
+        assert!(!is_login_path(
+            "/authors/jenkins/pipeline-model-converter/validate"
+        ));
+        assert!(!is_login_path(
+            "/jenkins-sso-team/pipeline-model-converter/validate"
+        ));
+    }
+
+    #[test]
+    fn test_unreadable_tls_files_are_config_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let not_pem = dir.path().join("not.pem");
+        std::fs::write(&not_pem, "not a certificate").unwrap();
+
+        for ca_cert in [dir.path().join("missing.pem"), not_pem] {
+            let mut config = client_for("https://jenkins.example.com".to_string()).config;
+            config.tls.ca_certs = vec![ca_cert];
+            assert!(matches!(
+                JenkinsClient::new(config, None),
+                Err(LspError::Config(_))
+            ));
+        }
+    }
+
     #[test]
     fn test_jenkins_client_with_insecure() {
         let config = Config {
@@ -150,9 +821,10 @@ mod tests {
             username: "test".to_string(),
             api_token: "token".to_string(),
             insecure: true,
+            ..Default::default()
         };
 
-        let client = JenkinsClient::new(config);
+        let client = JenkinsClient::new(config, None);
         assert!(client.is_ok());
     }
 }
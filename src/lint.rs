@@ -0,0 +1,285 @@
+use crate::config::{Config, DiscoveryConfig};
+use crate::discovery::{Discovery, validate_files};
+use crate::report::{FileReport, OutputFormat, write_reports};
+use crate::routing::JenkinsRouter;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// Exit status when every file is free of errors
+const EXIT_OK: u8 = 0;
+/// Exit status when at least one file has errors
+const EXIT_ERRORS: u8 = 1;
+/// Exit status when a file could not be checked at all
+const EXIT_FAILURE: u8 = 2;
+
+/// Arguments for `jenkinsfile-ls lint`
+#[derive(Debug, clap::Args)]
+pub struct LintArgs {
+    /// Jenkinsfiles, or directories to search for them; use `-` to read from stdin
+    ///
+    /// Defaults to searching the current directory.
+    pub paths: Vec<PathBuf>,
+
+    /// Only run local checks, without contacting Jenkins
+    #[arg(long)]
+    pub offline: bool,
+
+    /// Always ask Jenkins, rather than reusing results for content it has already validated
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Output format for the results
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+
+    /// Write the results to this file instead of stdout
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+
+    /// Glob for files to validate when searching directories (replaces the configured ones)
+    #[arg(long = "include", value_name = "GLOB")]
+    pub include: Vec<String>,
+
+    /// Glob for files to skip when searching directories (added to the configured ones)
+    #[arg(long = "exclude", value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
+    /// Search files even if `.gitignore` excludes them
+    #[arg(long)]
+    pub no_gitignore: bool,
+
+    /// Maximum number of files to validate at once
+    #[arg(long, short)]
+    pub jobs: Option<usize>,
+}
+
+/// Validate files from disk or stdin and report their diagnostics
+///
+/// By default diagnostics are printed compiler-style (`file:line:col: severity: message`)
+/// to stdout. Exits with 1 if any file has errors, or 2 if a file could not be read or
+/// validated (or the results could not be written).
+pub async fn run(args: LintArgs) -> ExitCode {
+    let dir = std::env::current_dir().ok();
+    // Offline checks need no credentials, so only the discovery settings are read
+    let config = (!args.offline).then(|| Config::load(None, dir.as_deref()));
+    let configured = match &config {
+        Some(config) => config.as_ref().ok().map(|c| c.discovery.clone()),
+        None => Config::load_discovery(None, dir.as_deref()).ok(),
+    };
+    let discovery_config = discovery_config(&args, configured);
+
+    let router = match config {
+        None => JenkinsRouter::default(),
+        Some(config) => {
+            let config = config.map(|mut config| {
+                if args.no_cache {
+                    config.cache.enabled = false;
+                }
+                config
+            });
+            match config.and_then(JenkinsRouter::new) {
+                Ok(router) => router,
+                Err(e) => {
+                    eprintln!("Failed to load configuration: {}", e);
+                    eprintln!(
+                        "Configure Jenkins (see README) or pass --offline for local checks only."
+                    );
+                    return ExitCode::from(EXIT_FAILURE);
+                }
+            }
+        }
+    };
+
+    let paths = match collect_paths(&args.paths, &discovery_config) {
+        Ok(paths) => paths,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+
+    let reports = validate_files(&paths, discovery_config.concurrency, |path| {
+        // Stdin has no path to pick an instance by
+        router.client_for((path != Path::new("-")).then_some(path))
+    })
+    .await;
+
+    for report in &reports {
+        if let Some(failure) = &report.failure {
+            eprintln!("{}: {}", report.path, failure);
+        }
+    }
+
+    if let Err(e) = write_output(&args, &reports) {
+        eprintln!("Failed to write results: {}", e);
+        return ExitCode::from(EXIT_FAILURE);
+    }
+
+    if args.format == OutputFormat::Text && reports.len() > 1 {
+        eprintln!("{}", summary(&reports));
+    }
+
+    let status = if reports.iter().any(|r| r.failure.is_some()) {
+        EXIT_FAILURE
+    } else if reports.iter().any(FileReport::has_errors) {
+        EXIT_ERRORS
+    } else {
+        EXIT_OK
+    };
+    ExitCode::from(status)
+}
+
+/// Apply command-line overrides to the configured discovery settings
+fn discovery_config(args: &LintArgs, configured: Option<DiscoveryConfig>) -> DiscoveryConfig {
+    let mut config = configured.unwrap_or_default();
+    if !args.include.is_empty() {
+        config.include = args.include.clone();
+    }
+    config.exclude.extend(args.exclude.iter().cloned());
+    if args.no_gitignore {
+        config.gitignore = false;
+    }
+    if let Some(jobs) = args.jobs {
+        config.concurrency = jobs;
+    }
+    config
+}
+
+/// Expand directories into the pipeline files found under them
+///
+/// Files (and `-` for stdin) named explicitly are always validated, whatever the
+/// include and exclude patterns say.
+fn collect_paths(
+    paths: &[PathBuf],
+    config: &DiscoveryConfig,
+) -> crate::types::Result<Vec<PathBuf>> {
+    let default_root = [PathBuf::from(".")];
+    let paths = if paths.is_empty() {
+        &default_root[..]
+    } else {
+        paths
+    };
+
+    let discovery = Discovery::new(config)?;
+    let mut collected = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let found = discovery.discover(path);
+            if found.is_empty() {
+                eprintln!("No pipeline files found under {}", path.display());
+            }
+            // Searching "." shouldn't prefix every result with "./"
+            collected.extend(
+                found
+                    .into_iter()
+                    .map(|p| p.strip_prefix(".").map(Path::to_path_buf).unwrap_or(p)),
+            );
+        } else {
+            collected.push(path.clone());
+        }
+    }
+    Ok(collected)
+}
+
+/// One-line summary of a batch of results
+fn summary(reports: &[FileReport]) -> String {
+    let with_errors = reports
+        .iter()
+        .filter(|r| r.failure.is_none() && r.has_errors())
+        .count();
+    let failed = reports.iter().filter(|r| r.failure.is_some()).count();
+    let mut summary = format!(
+        "Checked {} files: {} with errors",
+        reports.len(),
+        with_errors
+    );
+    if failed > 0 {
+        summary.push_str(&format!(", {} could not be validated", failed));
+    }
+    summary
+}
+
+/// Write the reports to the requested file, or stdout
+fn write_output(args: &LintArgs, reports: &[FileReport]) -> std::io::Result<()> {
+    match &args.output {
+        Some(path) => {
+            let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+            write_reports(args.format, reports, &mut file)?;
+            file.flush()
+        }
+        None => {
+            let mut stdout = std::io::stdout().lock();
+            write_reports(args.format, reports, &mut stdout)?;
+            stdout.flush()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        lint: LintArgs,
+    }
+
+    fn parse(args: &[&str]) -> LintArgs {
+        Cli::parse_from(std::iter::once("lint").chain(args.iter().copied())).lint
+    }
+
+    #[test]
+    fn test_discovery_overrides() {
+        let configured = DiscoveryConfig {
+            exclude: vec!["vendor/**".to_string()],
+            ..Default::default()
+        };
+        let args = parse(&[
+            "--include",
+            "**/*.groovy",
+            "--exclude",
+            "tmp/**",
+            "--no-gitignore",
+            "-j",
+            "2",
+        ]);
+        let config = discovery_config(&args, Some(configured));
+        assert_eq!(config.include, vec!["**/*.groovy"]);
+        assert_eq!(config.exclude, vec!["vendor/**", "tmp/**"]);
+        assert!(!config.gitignore);
+        assert_eq!(config.concurrency, 2);
+    }
+
+    #[test]
+    fn test_explicit_files_bypass_patterns() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("pipeline.txt");
+        std::fs::write(&file, "node {}\n").unwrap();
+        std::fs::write(dir.path().join("Jenkinsfile"), "node {}\n").unwrap();
+
+        let paths = vec![file.clone(), dir.path().to_path_buf(), PathBuf::from("-")];
+        let collected = collect_paths(&paths, &DiscoveryConfig::default()).unwrap();
+        assert_eq!(
+            collected,
+            vec![file, dir.path().join("Jenkinsfile"), PathBuf::from("-")]
+        );
+    }
+
+    #[test]
+    fn test_summary() {
+        let reports = vec![
+            FileReport::new("a".to_string(), Ok(Vec::new())),
+            FileReport::new(
+                "b".to_string(),
+                Err(crate::types::LspError::Auth("denied".to_string())),
+            ),
+        ];
+        assert_eq!(
+            summary(&reports),
+            "Checked 2 files: 0 with errors, 1 could not be validated"
+        );
+    }
+}
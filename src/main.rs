@@ -1,39 +1,92 @@
+mod cache;
 mod config;
+mod credentials;
 mod diagnostics;
+mod discovery;
+mod document;
 mod jenkins;
+mod lint;
+mod parser;
+mod report;
+mod retry;
+mod routing;
 mod server;
 mod types;
 
+use clap::{Parser, Subcommand};
 use config::Config;
-use jenkins::JenkinsClient;
-use server::Backend;
+use lint::LintArgs;
+use server::{Backend, Settings};
+use std::process::ExitCode;
 use tower_lsp::{LspService, Server};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+/// Language server for validating Jenkinsfiles against a Jenkins instance
+///
+/// Runs as an LSP server over stdio unless a subcommand is given.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Accepted because clients such as vscode-languageclient pass it; stdio is
+    /// always used
+    #[arg(long = "stdio", hide = true)]
+    _stdio: bool,
+    /// Accepted because vscode-languageclient may pass it; not used
+    #[arg(long = "clientProcessId", hide = true)]
+    _client_process_id: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Validate Jenkinsfiles and print errors, e.g. in CI or a git hook
+    Lint(LintArgs),
+}
+
 #[tokio::main]
-async fn main() {
-    // Initialize logging
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    // Initialize logging; the linter only logs warnings by default, to keep its output readable
+    let default_filter = match cli.command {
+        Some(Command::Lint(_)) => "jenkinsfile_ls=warn",
+        None => "jenkinsfile_ls=info,tower_lsp=info",
+    };
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "jenkinsfile_ls=info,tower_lsp=info".into()),
+                .unwrap_or_else(|_| default_filter.into()),
         )
         .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
         .init();
 
+    match cli.command {
+        Some(Command::Lint(args)) => lint::run(args).await,
+        None => {
+            serve().await;
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+/// Run the LSP server over stdio
+async fn serve() {
     tracing::info!("Starting jenkinsfile-ls v{}", env!("CARGO_PKG_VERSION"));
 
-    // Load configuration
-    let config = match Config::load(None) {
+    // Load configuration; without it the server still runs local checks
+    let config = match Config::load(None, None) {
         Ok(config) => {
             tracing::info!("Configuration loaded successfully");
             tracing::debug!("Jenkins URL: {}", config.jenkins_url);
             tracing::debug!("Username: {}", config.username);
-            config
+            Some(config)
         }
         Err(e) => {
             eprintln!("Failed to load configuration: {}", e);
-            eprintln!("\nPlease set the following environment variables:");
+            eprintln!(
+                "\nTo enable validation by Jenkins, set the following environment variables:"
+            );
             eprintln!(
                 "  JENKINS_URL         - Jenkins instance URL (e.g., https://jenkins.example.com)"
             );
@@ -41,20 +94,38 @@ async fn main() {
             eprintln!("  JENKINS_API_TOKEN   - Jenkins API token");
             eprintln!("\nOptional:");
             eprintln!("  JENKINS_INSECURE    - Set to '1' or 'true' to skip TLS verification");
+            eprintln!(
+                "  JENKINSFILE_LS_VALIDATE_ON_CHANGE - Set to '1' or 'true' to validate while typing"
+            );
+            eprintln!(
+                "  JENKINSFILE_LS_DEBOUNCE_MS        - Delay before validating a change (default 500)"
+            );
+            eprintln!(
+                "  JENKINSFILE_LS_VALIDATE_WORKSPACE - Set to '1' or 'true' to validate unopened files too"
+            );
+            eprintln!(
+                "  JENKINSFILE_LS_CACHE              - Set to '0' or 'false' to not reuse validation results"
+            );
             eprintln!("\nOr create a config file at: ~/.config/jenkinsfile-ls/config.toml");
-            std::process::exit(1);
+            eprintln!("\nContinuing with local checks only.");
+            None
         }
     };
 
-    // Create Jenkins client
-    let jenkins_client = match JenkinsClient::new(config) {
-        Ok(client) => {
-            tracing::info!("Jenkins client initialized");
-            client
+    // Create a Jenkins client for each instance
+    let settings = match config.map(Settings::from_config).transpose() {
+        Ok(Some(settings)) => {
+            tracing::info!(
+                "{} Jenkins client(s) initialized",
+                settings.jenkins.clients().count()
+            );
+            settings
         }
+        Ok(None) => Settings::local_only(),
         Err(e) => {
             eprintln!("Failed to initialize Jenkins client: {}", e);
-            std::process::exit(1);
+            eprintln!("Continuing with local checks only.");
+            Settings::local_only()
         }
     };
 
@@ -62,7 +133,7 @@ async fn main() {
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
-    let (service, socket) = LspService::new(|client| Backend::new(client, jenkins_client));
+    let (service, socket) = LspService::new(|client| Backend::new(client, settings));
 
     tracing::info!("LSP server starting on stdio");
 
@@ -70,3 +141,17 @@ async fn main() {
 
     tracing::info!("LSP server shutting down");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accepts_client_transport_flags() {
+        let cli = Cli::try_parse_from(["jenkinsfile-ls", "--stdio", "--clientProcessId=42"]);
+        assert!(cli.unwrap().command.is_none());
+
+        let cli = Cli::try_parse_from(["jenkinsfile-ls", "lint", "--offline", "Jenkinsfile"]);
+        assert!(matches!(cli.unwrap().command, Some(Command::Lint(_))));
+    }
+}
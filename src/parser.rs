@@ -0,0 +1,762 @@
+use crate::diagnostics::classify;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
+
+/// Diagnostic source for errors found locally, distinct from those reported by Jenkins
+pub const LOCAL_SOURCE: &str = "jenkinsfile-ls (local)";
+
+/// Sections allowed directly inside `pipeline { ... }`
+const PIPELINE_SECTIONS: &[&str] = &[
+    "agent",
+    "environment",
+    "libraries",
+    "options",
+    "parameters",
+    "post",
+    "stages",
+    "tools",
+    "triggers",
+];
+
+/// Sections allowed directly inside `stage('...') { ... }`
+const STAGE_SECTIONS: &[&str] = &[
+    "agent",
+    "environment",
+    "failFast",
+    "input",
+    "matrix",
+    "options",
+    "parallel",
+    "post",
+    "stages",
+    "steps",
+    "tools",
+    "when",
+];
+
+/// Stage sections that say what the stage executes; exactly one is required
+const STAGE_BODIES: &[&str] = &["matrix", "parallel", "stages", "steps"];
+
+/// Check a Jenkinsfile for syntax and structural errors without contacting Jenkins
+///
+/// This understands just enough Groovy to find unbalanced brackets, unterminated
+/// strings and comments. If the file is a Declarative Pipeline (it has a top-level
+/// `pipeline { ... }` block) the section structure is checked as well; scripted
+/// pipelines only get the syntax checks.
+pub fn check_jenkinsfile(text: &str) -> Vec<Diagnostic> {
+    let mut lexer = Lexer::new(text);
+    lexer.run();
+
+    let mut diagnostics = lexer.diagnostics;
+    diagnostics.extend(check_brackets(&lexer.tokens));
+
+    // The section tree is only meaningful if the file is lexically sound
+    if diagnostics.is_empty() {
+        let mut index = 0;
+        let top = parse_body(&lexer.tokens, &mut index);
+        if let Some(pipeline) = top.sections.iter().find(|s| s.name == "pipeline") {
+            check_pipeline(pipeline, &mut diagnostics);
+        }
+    }
+
+    diagnostics
+}
+
+/// Build an error diagnostic attributed to the local checker
+fn error(range: Range, message: impl Into<String>) -> Diagnostic {
+    let message = message.into();
+    let (code, code_description) = classify(&message).unzip();
+    Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::ERROR),
+        code,
+        code_description,
+        source: Some(LOCAL_SOURCE.to_string()),
+        message,
+        related_information: None,
+        tags: None,
+        data: None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Ident(String),
+    /// String literal contents, without the quotes
+    Str(String),
+    Open(char),
+    Close(char),
+    /// Any other single character, or a number
+    Other(char),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    range: Range,
+}
+
+/// Tokenizer for the subset of Groovy needed to track nesting
+///
+/// Positions are tracked in UTF-16 code units so they can be used in diagnostics directly.
+struct Lexer {
+    chars: Vec<char>,
+    index: usize,
+    line: u32,
+    character: u32,
+    tokens: Vec<Token>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Lexer {
+    fn new(text: &str) -> Self {
+        Self {
+            chars: text.chars().collect(),
+            index: 0,
+            line: 0,
+            character: 0,
+            tokens: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn peek(&self, ahead: usize) -> Option<char> {
+        self.chars.get(self.index + ahead).copied()
+    }
+
+    fn position(&self) -> Position {
+        Position::new(self.line, self.character)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek(0)?;
+        self.index += 1;
+        match c {
+            '\n' => {
+                self.line += 1;
+                self.character = 0;
+            }
+            // The newline of a "\r\n" pair advances the line instead
+            '\r' if self.peek(0) == Some('\n') => {}
+            '\r' => {
+                self.line += 1;
+                self.character = 0;
+            }
+            _ => self.character += c.len_utf16() as u32,
+        }
+        Some(c)
+    }
+
+    fn push(&mut self, kind: TokenKind, start: Position) {
+        let range = Range::new(start, self.position());
+        self.tokens.push(Token { kind, range });
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(c) = self.peek(0)
+            && c != '\n'
+            && c != '\r'
+        {
+            self.bump();
+        }
+    }
+
+    fn run(&mut self) {
+        while let Some(c) = self.peek(0) {
+            let start = self.position();
+            match c {
+                c if c.is_whitespace() => {
+                    self.bump();
+                }
+                '#' if self.index == 0 && self.peek(1) == Some('!') => self.skip_line(),
+                '/' if self.peek(1) == Some('/') => self.skip_line(),
+                '/' if self.peek(1) == Some('*') => self.block_comment(start),
+                '\'' | '"' => self.string(c, start),
+                '/' if self.regex_allowed() && self.slashy_string(start) => {}
+                '(' | '[' | '{' => {
+                    self.bump();
+                    self.push(TokenKind::Open(c), start);
+                }
+                ')' | ']' | '}' => {
+                    self.bump();
+                    self.push(TokenKind::Close(c), start);
+                }
+                c if c.is_alphabetic() || c == '_' || c == '$' => {
+                    let mut ident = String::new();
+                    while let Some(c) = self.peek(0)
+                        && (c.is_alphanumeric() || c == '_' || c == '$')
+                    {
+                        ident.push(c);
+                        self.bump();
+                    }
+                    self.push(TokenKind::Ident(ident), start);
+                }
+                c if c.is_ascii_digit() => {
+                    while let Some(c) = self.peek(0)
+                        && (c.is_alphanumeric() || c == '_' || c == '.')
+                    {
+                        self.bump();
+                    }
+                    self.push(TokenKind::Other(c), start);
+                }
+                _ => {
+                    self.bump();
+                    self.push(TokenKind::Other(c), start);
+                }
+            }
+        }
+    }
+
+    fn block_comment(&mut self, start: Position) {
+        self.bump();
+        self.bump();
+        loop {
+            match self.bump() {
+                Some('*') if self.peek(0) == Some('/') => {
+                    self.bump();
+                    return;
+                }
+                Some(_) => {}
+                None => {
+                    let end = Position::new(start.line, start.character + 2);
+                    self.diagnostics
+                        .push(error(Range::new(start, end), "Unterminated comment"));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Lex a quoted string: `'...'`, `"..."`, `'''...'''` or `"""..."""`
+    fn string(&mut self, quote: char, start: Position) {
+        let triple = self.peek(1) == Some(quote) && self.peek(2) == Some(quote);
+        let delimiter = if triple { 3 } else { 1 };
+        for _ in 0..delimiter {
+            self.bump();
+        }
+        let opening_end = self.position();
+
+        let mut contents = String::new();
+        loop {
+            match self.peek(0) {
+                None => break,
+                Some('\n' | '\r') if !triple => break,
+                Some('\\') => {
+                    contents.extend(self.bump());
+                    contents.extend(self.bump());
+                }
+                Some(c)
+                    if c == quote
+                        && (!triple
+                            || (self.peek(1) == Some(quote) && self.peek(2) == Some(quote))) =>
+                {
+                    for _ in 0..delimiter {
+                        self.bump();
+                    }
+                    self.push(TokenKind::Str(contents), start);
+                    return;
+                }
+                Some('$') if quote == '"' && self.peek(1) == Some('{') => {
+                    self.interpolation(&mut contents);
+                }
+                Some(c) => {
+                    contents.push(c);
+                    self.bump();
+                }
+            }
+        }
+
+        // Single-line strings are reported up to the end of the line; multi-line
+        // strings run to the end of the file, so just point at the opening quotes
+        let end = if triple { opening_end } else { self.position() };
+        self.diagnostics
+            .push(error(Range::new(start, end), "Unterminated string literal"));
+    }
+
+    /// Consume a `${...}` GString interpolation, which may itself contain braces
+    fn interpolation(&mut self, contents: &mut String) {
+        let mut depth = 0;
+        while let Some(c) = self.peek(0) {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => {}
+            }
+            contents.push(c);
+            self.bump();
+            if depth == 0 && c == '}' {
+                return;
+            }
+        }
+    }
+
+    /// Whether a `/` here would start a slashy string rather than be a division
+    fn regex_allowed(&self) -> bool {
+        match self.tokens.last().map(|t| &t.kind) {
+            None | Some(TokenKind::Open(_)) => true,
+            Some(TokenKind::Other(c)) => "=~!,:?&|+".contains(*c),
+            Some(TokenKind::Ident(ident)) => ident == "return",
+            Some(_) => false,
+        }
+    }
+
+    /// Try to lex a `/.../` slashy string on the current line
+    ///
+    /// Returns false (consuming nothing) if there is no closing slash, in which
+    /// case the `/` is treated as an operator.
+    fn slashy_string(&mut self, start: Position) -> bool {
+        let mut offset = 1;
+        loop {
+            match self.peek(offset) {
+                None | Some('\n' | '\r') => return false,
+                Some('\\') => offset += 2,
+                Some('/') => break,
+                Some(_) => offset += 1,
+            }
+        }
+
+        self.bump();
+        let mut contents = String::new();
+        for _ in 1..offset {
+            contents.extend(self.bump());
+        }
+        self.bump();
+        self.push(TokenKind::Str(contents), start);
+        true
+    }
+}
+
+/// Report unbalanced or mismatched brackets
+fn check_brackets(tokens: &[Token]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut stack: Vec<(char, Range)> = Vec::new();
+
+    for token in tokens {
+        match token.kind {
+            TokenKind::Open(open) => stack.push((open, token.range)),
+            TokenKind::Close(close) => {
+                let open = matching_open(close);
+                match stack.iter().rposition(|(c, _)| *c == open) {
+                    Some(index) => {
+                        // Anything opened after the matching bracket was never closed
+                        for (unclosed, range) in stack.drain(index + 1..) {
+                            diagnostics.push(error(range, format!("Unclosed '{}'", unclosed)));
+                        }
+                        stack.pop();
+                    }
+                    None => diagnostics.push(error(
+                        token.range,
+                        format!("Unexpected '{}' without matching '{}'", close, open),
+                    )),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for (unclosed, range) in stack {
+        diagnostics.push(error(range, format!("Unclosed '{}'", unclosed)));
+    }
+
+    diagnostics
+}
+
+fn matching_open(close: char) -> char {
+    match close {
+        ')' => '(',
+        ']' => '[',
+        _ => '{',
+    }
+}
+
+/// A named block such as `stages { ... }` or `stage('Build') { ... }`
+#[derive(Debug)]
+struct Section<'a> {
+    name: &'a str,
+    name_range: Range,
+    /// From the name to the end of any parenthesised arguments
+    header: Range,
+    /// First string literal among the arguments, e.g. the stage name
+    label: Option<&'a str>,
+    body: Body<'a>,
+}
+
+/// The contents of a block: nested sections, and other statements by their first word
+#[derive(Debug, Default)]
+struct Body<'a> {
+    sections: Vec<Section<'a>>,
+    statements: Vec<(&'a str, Range)>,
+}
+
+impl Body<'_> {
+    /// Names of all sections and statements, in order of appearance
+    fn names(&self) -> impl Iterator<Item = (&str, Range)> {
+        let mut names: Vec<_> = self
+            .sections
+            .iter()
+            .map(|s| (s.name, s.name_range))
+            .chain(self.statements.iter().copied())
+            .collect();
+        names.sort_by_key(|(_, range)| (range.start.line, range.start.character));
+        names.into_iter()
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.names().any(|(n, _)| n == name)
+    }
+}
+
+/// Parse a block body up to (not including) its closing brace
+///
+/// Assumes brackets are balanced. A word starts a statement if it is the first
+/// token on its line or follows a `;`.
+fn parse_body<'a>(tokens: &'a [Token], index: &mut usize) -> Body<'a> {
+    let mut body = Body::default();
+    let mut statement_start = true;
+    let mut last_line = None;
+
+    while let Some(token) = tokens.get(*index) {
+        if last_line.is_some_and(|line| token.range.start.line > line) {
+            statement_start = true;
+        }
+
+        match &token.kind {
+            TokenKind::Close(_) => break,
+            TokenKind::Ident(name) if statement_start => {
+                *index += 1;
+                let mut header = token.range;
+                let mut label = None;
+
+                if tokens.get(*index).map(|t| &t.kind) == Some(&TokenKind::Open('(')) {
+                    let open = *index;
+                    let close = skip_group(tokens, index);
+                    header.end = tokens[close].range.end;
+                    label = tokens[open..close].iter().find_map(|t| match &t.kind {
+                        TokenKind::Str(s) => Some(s.as_str()),
+                        _ => None,
+                    });
+                }
+
+                if tokens.get(*index).map(|t| &t.kind) == Some(&TokenKind::Open('{')) {
+                    *index += 1;
+                    let inner = parse_body(tokens, index);
+                    *index += 1;
+                    body.sections.push(Section {
+                        name,
+                        name_range: token.range,
+                        header,
+                        label,
+                        body: inner,
+                    });
+                } else {
+                    body.statements.push((name, token.range));
+                }
+            }
+            TokenKind::Open(_) => {
+                skip_group(tokens, index);
+            }
+            _ => *index += 1,
+        }
+
+        statement_start = tokens[*index - 1].kind == TokenKind::Other(';');
+        last_line = Some(tokens[*index - 1].range.end.line);
+    }
+
+    body
+}
+
+/// Skip a bracketed group starting at `index`, returning the index of its closing bracket
+fn skip_group(tokens: &[Token], index: &mut usize) -> usize {
+    let mut depth = 0;
+    while let Some(token) = tokens.get(*index) {
+        *index += 1;
+        match token.kind {
+            TokenKind::Open(_) => depth += 1,
+            TokenKind::Close(_) => {
+                depth -= 1;
+                if depth == 0 {
+                    return *index - 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    tokens.len() - 1
+}
+
+fn check_pipeline(pipeline: &Section, diagnostics: &mut Vec<Diagnostic>) {
+    let body = &pipeline.body;
+    check_sections(body, PIPELINE_SECTIONS, diagnostics, |name| {
+        format!("Undefined section \"{}\"", name)
+    });
+
+    for required in ["agent", "stages"] {
+        if !body.has(required) {
+            diagnostics.push(error(
+                pipeline.header,
+                format!("Missing required section \"{}\"", required),
+            ));
+        }
+    }
+
+    for stages in body.sections.iter().filter(|s| s.name == "stages") {
+        check_stages(stages, diagnostics);
+    }
+}
+
+/// Report unknown and repeated section names within a body
+fn check_sections(
+    body: &Body,
+    allowed: &[&str],
+    diagnostics: &mut Vec<Diagnostic>,
+    unknown: impl Fn(&str) -> String,
+) {
+    let mut seen: Vec<&str> = Vec::new();
+    for (name, range) in body.names() {
+        if !allowed.contains(&name) {
+            diagnostics.push(error(range, unknown(name)));
+        } else if seen.contains(&name) {
+            diagnostics.push(error(
+                range,
+                format!("Multiple occurrences of the \"{}\" section", name),
+            ));
+        } else {
+            seen.push(name);
+        }
+    }
+}
+
+fn check_stages(stages: &Section, diagnostics: &mut Vec<Diagnostic>) {
+    let body = &stages.body;
+    if body.sections.is_empty() && body.statements.is_empty() {
+        diagnostics.push(error(stages.header, "No stages specified"));
+    }
+
+    for (_, range) in &body.statements {
+        diagnostics.push(error(*range, "Expected a stage"));
+    }
+
+    for section in &body.sections {
+        if section.name == "stage" {
+            check_stage(section, diagnostics);
+        } else {
+            diagnostics.push(error(section.name_range, "Expected a stage"));
+        }
+    }
+}
+
+fn check_stage(stage: &Section, diagnostics: &mut Vec<Diagnostic>) {
+    let Some(name) = stage.label else {
+        diagnostics.push(error(stage.header, "Expected a stage name"));
+        return;
+    };
+
+    let body = &stage.body;
+    check_sections(body, STAGE_SECTIONS, diagnostics, |section| {
+        format!(
+            "Unknown stage section \"{}\". Starting with version 0.5, steps in a stage must be in a ‘steps’ block.",
+            section
+        )
+    });
+
+    let executes = body
+        .sections
+        .iter()
+        .filter(|s| STAGE_BODIES.contains(&s.name))
+        .count();
+    if executes == 0 {
+        diagnostics.push(error(
+            stage.header,
+            format!(
+                "Expected one of \"steps\", \"stages\", or \"parallel\" for stage \"{}\"",
+                name
+            ),
+        ));
+    } else if executes > 1 {
+        diagnostics.push(error(
+            stage.header,
+            format!(
+                "Only one of \"matrix\", \"parallel\", \"stages\", or \"steps\" allowed for stage \"{}\"",
+                name
+            ),
+        ));
+    }
+
+    for section in &body.sections {
+        match section.name {
+            "stages" | "parallel" => check_stages(section, diagnostics),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(text: &str) -> Vec<String> {
+        check_jenkinsfile(text)
+            .into_iter()
+            .map(|d| d.message)
+            .collect()
+    }
+
+    const VALID: &str = r#"#!groovy
+// A typical declarative pipeline
+pipeline {
+    agent { label 'linux' }
+    environment {
+        GREETING = "hello ${env.USER ?: 'nobody'}"
+    }
+    stages {
+        stage('Build') {
+            steps {
+                sh '''
+                    make { all }
+                '''
+                script {
+                    if (env.BRANCH_NAME =~ /release\/.*/) { echo "release" }
+                }
+            }
+        }
+        stage("Test") {
+            parallel {
+                stage('Unit') { steps { sh 'make test' } }
+                stage('Lint') { steps { sh 'make lint' } }
+            }
+        }
+    }
+    /* post { always { deleteDir() } } */
+    post { always { deleteDir() } }
+}
+"#;
+
+    #[test]
+    fn test_valid_pipeline() {
+        assert!(messages(VALID).is_empty(), "{:?}", messages(VALID));
+    }
+
+    #[test]
+    fn test_scripted_pipeline_has_no_structure_checks() {
+        let text = "node {\n    stage('Build') {\n        sh 'make'\n    }\n}\n";
+        assert!(messages(text).is_empty());
+    }
+
+    #[test]
+    fn test_unclosed_brace() {
+        let text = "pipeline {\n    agent any\n    stages {\n";
+        let diagnostics = check_jenkinsfile(text);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].message, "Unclosed '{'");
+        assert_eq!(diagnostics[0].range.start, Position::new(0, 9));
+        assert_eq!(diagnostics[1].range.start, Position::new(2, 11));
+        assert_eq!(diagnostics[0].source, Some(LOCAL_SOURCE.to_string()));
+    }
+
+    #[test]
+    fn test_unexpected_closing_bracket() {
+        let diagnostics = check_jenkinsfile("node {\n}\n}\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "Unexpected '}' without matching '{'"
+        );
+        assert_eq!(diagnostics[0].range.start, Position::new(2, 0));
+    }
+
+    #[test]
+    fn test_mismatched_brackets() {
+        let diagnostics = check_jenkinsfile("node {\n    sh('make'\n}\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "Unclosed '('");
+        assert_eq!(diagnostics[0].range.start, Position::new(1, 6));
+    }
+
+    #[test]
+    fn test_unterminated_strings() {
+        let diagnostics = check_jenkinsfile("node {\n    sh 'make\n}\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "Unterminated string literal");
+        assert_eq!(diagnostics[0].range.start, Position::new(1, 7));
+        assert_eq!(diagnostics[0].range.end, Position::new(1, 12));
+
+        let diagnostics = check_jenkinsfile("node {\n    sh \"\"\"\n    make\n}\n");
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].message, "Unterminated string literal");
+        assert_eq!(diagnostics[0].range.end, Position::new(1, 10));
+        assert_eq!(diagnostics[1].message, "Unclosed '{'");
+    }
+
+    #[test]
+    fn test_unterminated_comment() {
+        assert_eq!(
+            messages("node {}\n/* trailing"),
+            vec!["Unterminated comment"]
+        );
+    }
+
+    #[test]
+    fn test_utf16_columns() {
+        let diagnostics = check_jenkinsfile("echo '😀' }");
+        assert_eq!(diagnostics[0].range.start, Position::new(0, 10));
+    }
+
+    #[test]
+    fn test_missing_required_sections() {
+        assert_eq!(
+            messages("pipeline {\n}\n"),
+            vec![
+                "Missing required section \"agent\"",
+                "Missing required section \"stages\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_and_duplicate_pipeline_sections() {
+        let text = "pipeline {\n  agent any\n  agent none\n  stage('x') { steps {} }\n  stages { stage('a') { steps {} } }\n}\n";
+        assert_eq!(
+            messages(text),
+            vec![
+                "Multiple occurrences of the \"agent\" section",
+                "Undefined section \"stage\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stage_structure() {
+        let text = r#"pipeline {
+  agent any
+  stages {
+    stage('Empty') { }
+    stage('Bare') {
+      sh 'make'
+      steps { sh 'make' }
+    }
+    stage('Both') {
+      steps { sh 'make' }
+      parallel { stage('p') { steps {} } }
+    }
+    echo 'hi'
+  }
+}
+"#;
+        assert_eq!(
+            messages(text),
+            vec![
+                "Expected a stage",
+                "Expected one of \"steps\", \"stages\", or \"parallel\" for stage \"Empty\"",
+                "Unknown stage section \"sh\". Starting with version 0.5, steps in a stage must be in a ‘steps’ block.",
+                "Only one of \"matrix\", \"parallel\", \"stages\", or \"steps\" allowed for stage \"Both\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_stages() {
+        assert_eq!(
+            messages("pipeline {\n  agent any\n  stages {\n  }\n}\n"),
+            vec!["No stages specified"]
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use super::{FileReport, rule_id, severity_name, xml_attribute, xml_escape};
+use std::io::{self, Write};
+use tower_lsp::lsp_types::DiagnosticSeverity;
+
+/// Write a Checkstyle XML report, as consumed by the Warnings NG plugin among others
+///
+/// Every file gets a `<file>` element, so files without problems show up as checked.
+/// A file that could not be checked gets a single error without a line number.
+pub fn write(reports: &[FileReport], out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(out, r#"<checkstyle version="4.3">"#)?;
+
+    for report in reports {
+        writeln!(out, r#"  <file name="{}">"#, xml_escape(&report.path))?;
+
+        if let Some(failure) = &report.failure {
+            writeln!(
+                out,
+                r#"    <error severity="error" message="{}" source="jenkinsfile-ls.validation-failed"/>"#,
+                xml_attribute(failure)
+            )?;
+        }
+
+        for diagnostic in &report.diagnostics {
+            // Checkstyle has no "hint" severity
+            let severity = match diagnostic.severity {
+                Some(DiagnosticSeverity::HINT) => "info",
+                severity => severity_name(severity),
+            };
+            writeln!(
+                out,
+                r#"    <error line="{}" column="{}" severity="{}" message="{}" source="jenkinsfile-ls.{}"/>"#,
+                diagnostic.range.start.line + 1,
+                diagnostic.range.start.character + 1,
+                severity,
+                xml_attribute(&diagnostic.message),
+                xml_escape(&rule_id(diagnostic))
+            )?;
+        }
+
+        writeln!(out, "  </file>")?;
+    }
+
+    writeln!(out, "</checkstyle>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::check_jenkinsfile;
+
+    #[test]
+    fn test_checkstyle_report() {
+        let reports = vec![
+            FileReport {
+                path: "Jenkinsfile".to_string(),
+                diagnostics: check_jenkinsfile("node {\n    sh 'make\n}\n"),
+                failure: None,
+            },
+            FileReport {
+                path: "a&b/Jenkinsfile".to_string(),
+                diagnostics: Vec::new(),
+                failure: Some("Authentication failed: bad token".to_string()),
+            },
+        ];
+
+        let mut out = Vec::new();
+        write(&reports, &mut out).unwrap();
+        let xml = String::from_utf8(out).unwrap();
+
+        assert!(xml.contains(r#"<file name="Jenkinsfile">"#));
+        assert!(xml.contains(
+            r#"<error line="2" column="8" severity="error" message="Unterminated string literal" source="jenkinsfile-ls.local-syntax"/>"#
+        ));
+        assert!(xml.contains(r#"<file name="a&amp;b/Jenkinsfile">"#));
+        assert!(xml.contains(
+            r#"<error severity="error" message="Authentication failed: bad token" source="jenkinsfile-ls.validation-failed"/>"#
+        ));
+        assert!(xml.trim_end().ends_with("</checkstyle>"));
+    }
+}
@@ -0,0 +1,136 @@
+use super::{FileReport, format_diagnostic, is_error, rule_id, xml_attribute, xml_escape};
+use std::io::{self, Write};
+
+/// Write a JUnit XML report with one test case per file
+///
+/// Each error diagnostic becomes a `<failure>` of that test case, and a file that
+/// could not be checked becomes an `<error>`. Other diagnostics (warnings, hints)
+/// are listed in `<system-out>` without failing the test.
+pub fn write(reports: &[FileReport], out: &mut dyn Write) -> io::Result<()> {
+    let tests = reports.len();
+    let failures = reports
+        .iter()
+        .filter(|r| r.failure.is_none() && r.has_errors())
+        .count();
+    let errors = reports.iter().filter(|r| r.failure.is_some()).count();
+
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<testsuites name="jenkinsfile-ls" tests="{}" failures="{}" errors="{}">"#,
+        tests, failures, errors
+    )?;
+    writeln!(
+        out,
+        r#"  <testsuite name="jenkinsfile-ls" tests="{}" failures="{}" errors="{}">"#,
+        tests, failures, errors
+    )?;
+
+    for report in reports {
+        let name = xml_escape(&report.path);
+        writeln!(
+            out,
+            r#"    <testcase classname="jenkinsfile-ls" name="{}">"#,
+            name
+        )?;
+
+        if let Some(failure) = &report.failure {
+            writeln!(
+                out,
+                r#"      <error message="{}" type="validation-failed">{}</error>"#,
+                xml_attribute(failure),
+                xml_escape(failure)
+            )?;
+        }
+
+        let (errors, others): (Vec<_>, Vec<_>) =
+            report.diagnostics.iter().partition(|d| is_error(d));
+
+        for diagnostic in errors {
+            writeln!(
+                out,
+                r#"      <failure message="{}" type="{}">{}</failure>"#,
+                xml_attribute(&diagnostic.message),
+                xml_escape(&rule_id(diagnostic)),
+                xml_escape(&format_diagnostic(&report.path, diagnostic))
+            )?;
+        }
+
+        if !others.is_empty() {
+            writeln!(out, "      <system-out>")?;
+            for diagnostic in others {
+                writeln!(
+                    out,
+                    "{}",
+                    xml_escape(&format_diagnostic(&report.path, diagnostic))
+                )?;
+            }
+            writeln!(out, "      </system-out>")?;
+        }
+
+        writeln!(out, "    </testcase>")?;
+    }
+
+    writeln!(out, "  </testsuite>")?;
+    writeln!(out, "</testsuites>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::parse_jenkins_response;
+    use tower_lsp::lsp_types::DiagnosticSeverity;
+
+    fn render(reports: &[FileReport]) -> String {
+        let mut out = Vec::new();
+        write(reports, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_junit_report() {
+        let mut warning = parse_jenkins_response(
+            "WorkflowScript: 2: deprecated <thing> @ line 2, column 3.",
+            "",
+        );
+        warning[0].severity = Some(DiagnosticSeverity::WARNING);
+
+        let reports = vec![
+            FileReport {
+                path: "Jenkinsfile".to_string(),
+                diagnostics: Vec::new(),
+                failure: None,
+            },
+            FileReport {
+                path: "ci/build.jenkinsfile".to_string(),
+                diagnostics: parse_jenkins_response(
+                    "WorkflowScript: 7: expecting '}', found \"stage\" @ line 7, column 5.",
+                    "",
+                )
+                .into_iter()
+                .chain(warning)
+                .collect(),
+                failure: None,
+            },
+            FileReport {
+                path: "ci/deploy.groovy".to_string(),
+                diagnostics: Vec::new(),
+                failure: Some("Network error: connection refused".to_string()),
+            },
+        ];
+
+        let xml = render(&reports);
+        assert!(
+            xml.contains(r#"<testsuites name="jenkinsfile-ls" tests="3" failures="1" errors="1">"#)
+        );
+        assert!(xml.contains(r#"<testcase classname="jenkinsfile-ls" name="Jenkinsfile">"#));
+        assert!(xml.contains(
+            r#"<failure message="expecting &apos;}&apos;, found &quot;stage&quot;" type="unexpected-token">ci/build.jenkinsfile:7:5: error: expecting &apos;}&apos;, found &quot;stage&quot;</failure>"#
+        ));
+        assert!(xml.contains("ci/build.jenkinsfile:2:3: warning: deprecated &lt;thing&gt;\n"));
+        assert!(xml.contains(
+            r#"<error message="Network error: connection refused" type="validation-failed">"#
+        ));
+        assert!(xml.trim_end().ends_with("</testsuites>"));
+    }
+}
@@ -0,0 +1,220 @@
+mod checkstyle;
+mod junit;
+mod sarif;
+
+use crate::parser::LOCAL_SOURCE;
+use std::io::{self, Write};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString};
+
+/// Output format for batch validation results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Compiler-style `file:line:col: severity: message` lines
+    #[default]
+    Text,
+    /// SARIF 2.1.0 log, for code-scanning dashboards
+    Sarif,
+    /// JUnit XML, with one test case per file
+    Junit,
+    /// Checkstyle XML, e.g. for the Warnings NG plugin
+    Checkstyle,
+}
+
+/// Validation results for a single file
+#[derive(Debug, Clone)]
+pub struct FileReport {
+    /// Path as given by the user, or `<stdin>`
+    pub path: String,
+    /// Diagnostics from local checks and Jenkins
+    pub diagnostics: Vec<Diagnostic>,
+    /// Why the file could not be checked, if it couldn't
+    pub failure: Option<String>,
+}
+
+impl FileReport {
+    /// Build a report from the outcome of validating a file
+    pub fn new(path: String, result: crate::types::Result<Vec<Diagnostic>>) -> Self {
+        match result {
+            Ok(diagnostics) => Self {
+                path,
+                diagnostics,
+                failure: None,
+            },
+            Err(e) => Self {
+                path,
+                diagnostics: Vec::new(),
+                failure: Some(e.to_string()),
+            },
+        }
+    }
+
+    /// Whether any diagnostic is an error
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(is_error)
+    }
+}
+
+/// Whether a diagnostic is an error, treating a missing severity as one
+fn is_error(diagnostic: &Diagnostic) -> bool {
+    matches!(diagnostic.severity, None | Some(DiagnosticSeverity::ERROR))
+}
+
+/// Write the reports in the given format
+pub fn write_reports(
+    format: OutputFormat,
+    reports: &[FileReport],
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => write_text(reports, out),
+        OutputFormat::Sarif => sarif::write(reports, out),
+        OutputFormat::Junit => junit::write(reports, out),
+        OutputFormat::Checkstyle => checkstyle::write(reports, out),
+    }
+}
+
+fn write_text(reports: &[FileReport], out: &mut dyn Write) -> io::Result<()> {
+    for report in reports {
+        for diagnostic in &report.diagnostics {
+            writeln!(out, "{}", format_diagnostic(&report.path, diagnostic))?;
+        }
+    }
+    Ok(())
+}
+
+/// Format a diagnostic as `file:line:col: severity: message`, with 1-based line and column
+///
+/// Multi-line messages are joined into one line, so each diagnostic is one line.
+pub fn format_diagnostic(name: &str, diagnostic: &Diagnostic) -> String {
+    format!(
+        "{}:{}:{}: {}: {}",
+        name,
+        diagnostic.range.start.line + 1,
+        diagnostic.range.start.character + 1,
+        severity_name(diagnostic.severity),
+        single_line(&diagnostic.message)
+    )
+}
+
+/// Join the non-empty lines of a message with spaces
+fn single_line(message: &str) -> String {
+    message
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Lower-case name of a severity, treating a missing one as an error
+fn severity_name(severity: Option<DiagnosticSeverity>) -> &'static str {
+    match severity {
+        Some(DiagnosticSeverity::WARNING) => "warning",
+        Some(DiagnosticSeverity::INFORMATION) => "info",
+        Some(DiagnosticSeverity::HINT) => "hint",
+        _ => "error",
+    }
+}
+
+/// Escape a message for an XML attribute, on one line since parsers turn newlines
+/// in attributes into spaces anyway
+fn xml_attribute(text: &str) -> String {
+    xml_escape(&single_line(text))
+}
+
+/// Escape text for use in XML attribute values and element content
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than tab and newlines are not allowed in XML 1.0
+            c if c.is_control() && !matches!(c, '\t' | '\n' | '\r') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Stable identifier for the kind of problem a diagnostic reports
+///
+/// Uses the diagnostic code when there is one, otherwise whether it came from the
+/// local checks or from Jenkins.
+fn rule_id(diagnostic: &Diagnostic) -> String {
+    match &diagnostic.code {
+        Some(NumberOrString::String(code)) => code.clone(),
+        Some(NumberOrString::Number(code)) => code.to_string(),
+        None if diagnostic.source.as_deref() == Some(LOCAL_SOURCE) => "local-syntax".to_string(),
+        None => "jenkins-validation".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::parse_jenkins_response;
+    use crate::parser::check_jenkinsfile;
+
+    #[test]
+    fn test_format_remote_diagnostic() {
+        let diagnostics = parse_jenkins_response(
+            "WorkflowScript: 46: unexpected token: } @ line 46, column 1.",
+            "",
+        );
+        assert_eq!(
+            format_diagnostic("Jenkinsfile", &diagnostics[0]),
+            "Jenkinsfile:46:1: error: unexpected token: }"
+        );
+    }
+
+    #[test]
+    fn test_format_local_diagnostic() {
+        let diagnostics = check_jenkinsfile("node {\n    sh 'make\n}\n");
+        assert_eq!(
+            format_diagnostic("ci/build.groovy", &diagnostics[0]),
+            "ci/build.groovy:2:8: error: Unterminated string literal"
+        );
+    }
+
+    #[test]
+    fn test_format_multi_line_message() {
+        let diagnostics = parse_jenkins_response(
+            "WorkflowScript: 3: Invalid agent type \"docker\" specified.\n  Must be one of [any, label, none] @ line 3, column 5.\n       agent { docker 'maven' }\n       ^",
+            "",
+        );
+        assert!(diagnostics[0].message.contains('\n'));
+
+        let reports = vec![FileReport::new("Jenkinsfile".to_string(), Ok(diagnostics))];
+        let mut out = Vec::new();
+        write_reports(OutputFormat::Text, &reports, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Jenkinsfile:3:5: error: Invalid agent type \"docker\" specified. Must be one of [any, label, none]\n"
+        );
+    }
+
+    #[test]
+    fn test_xml_escape() {
+        assert_eq!(
+            xml_escape("expecting '}', found \"<EOF>\" & more\u{1b}"),
+            "expecting &apos;}&apos;, found &quot;&lt;EOF&gt;&quot; &amp; more"
+        );
+    }
+
+    #[test]
+    fn test_rule_id_falls_back_to_source() {
+        let local = check_jenkinsfile("}");
+        assert_eq!(rule_id(&local[0]), "local-syntax");
+
+        let mut remote =
+            parse_jenkins_response("WorkflowScript: 1: oops @ line 1, column 1.", "").remove(0);
+        assert_eq!(rule_id(&remote), "jenkins-validation");
+
+        remote.code = Some(NumberOrString::String("unknown-step".to_string()));
+        assert_eq!(rule_id(&remote), "unknown-step");
+    }
+}
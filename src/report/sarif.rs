@@ -0,0 +1,402 @@
+use super::{FileReport, rule_id};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::Path;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity};
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const SARIF_VERSION: &str = "2.1.0";
+const INFORMATION_URI: &str = "https://github.com/Smaug123/jenkinsfile-lint-lsp";
+/// Base that relative paths are resolved against: the directory `lint` was run in
+const SRCROOT: &str = "%SRCROOT%";
+
+/// Write a SARIF 2.1.0 log with one run covering all reports
+pub fn write(reports: &[FileReport], out: &mut dyn Write) -> io::Result<()> {
+    let root = std::env::current_dir().ok();
+    serde_json::to_writer_pretty(&mut *out, &build_log(reports, root.as_deref()))?;
+    writeln!(out)
+}
+
+fn build_log(reports: &[FileReport], root: Option<&Path>) -> Log {
+    let mut rules: Vec<Rule> = Vec::new();
+    let mut results = Vec::new();
+
+    for report in reports {
+        for diagnostic in &report.diagnostics {
+            let rule_id = rule_id(diagnostic);
+            let rule_index = match rules.iter().position(|r| r.id == rule_id) {
+                Some(index) => index,
+                None => {
+                    rules.push(Rule::for_diagnostic(rule_id.clone(), diagnostic));
+                    rules.len() - 1
+                }
+            };
+            results.push(SarifResult::new(
+                &report.path,
+                rule_id,
+                rule_index,
+                diagnostic,
+            ));
+        }
+    }
+
+    let notifications: Vec<Notification> = reports
+        .iter()
+        .filter_map(|report| {
+            let failure = report.failure.as_ref()?;
+            Some(Notification {
+                level: "error",
+                message: Message {
+                    text: failure.clone(),
+                },
+                locations: vec![Location::file(&report.path, None)],
+            })
+        })
+        .collect();
+
+    Log {
+        schema: SARIF_SCHEMA,
+        version: SARIF_VERSION,
+        runs: vec![Run {
+            tool: Tool {
+                driver: Driver {
+                    name: "jenkinsfile-ls",
+                    version: env!("CARGO_PKG_VERSION"),
+                    information_uri: INFORMATION_URI,
+                    rules,
+                },
+            },
+            invocations: vec![Invocation {
+                execution_successful: notifications.is_empty(),
+                tool_execution_notifications: notifications,
+            }],
+            original_uri_base_ids: root
+                .map(|root| {
+                    let mut uri = ArtifactLocation::for_path(&root.to_string_lossy()).uri;
+                    if !uri.ends_with('/') {
+                        uri.push('/');
+                    }
+                    BTreeMap::from([(
+                        SRCROOT,
+                        ArtifactLocation {
+                            uri,
+                            uri_base_id: None,
+                        },
+                    )])
+                })
+                .unwrap_or_default(),
+            column_kind: "utf16CodeUnits",
+            results,
+        }],
+    }
+}
+
+/// Percent-encode a path for a URI, keeping `/` and, if `keep_colon`, `:`
+fn encode_path(path: &str, keep_colon: bool) -> String {
+    let mut uri = String::new();
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) || (keep_colon && byte == b':')
+        {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+/// Whether a path (with `/` separators) starts with a Windows drive, like `C:/`
+fn has_drive(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && bytes[2] == b'/'
+}
+
+fn level(severity: Option<DiagnosticSeverity>) -> &'static str {
+    match severity {
+        Some(DiagnosticSeverity::WARNING) => "warning",
+        Some(DiagnosticSeverity::INFORMATION) | Some(DiagnosticSeverity::HINT) => "note",
+        _ => "error",
+    }
+}
+
+#[derive(Serialize)]
+struct Log {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: Vec<Run>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Run {
+    tool: Tool,
+    invocations: Vec<Invocation>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    original_uri_base_ids: BTreeMap<&'static str, ArtifactLocation>,
+    column_kind: &'static str,
+    results: Vec<SarifResult>,
+}
+
+#[derive(Serialize)]
+struct Tool {
+    driver: Driver,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Driver {
+    name: &'static str,
+    version: &'static str,
+    information_uri: &'static str,
+    rules: Vec<Rule>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Rule {
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    help_uri: Option<String>,
+}
+
+impl Rule {
+    fn for_diagnostic(id: String, diagnostic: &Diagnostic) -> Self {
+        Self {
+            id,
+            help_uri: diagnostic
+                .code_description
+                .as_ref()
+                .map(|d| d.href.to_string()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Invocation {
+    execution_successful: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_execution_notifications: Vec<Notification>,
+}
+
+#[derive(Serialize)]
+struct Notification {
+    level: &'static str,
+    message: Message,
+    locations: Vec<Location>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    rule_id: String,
+    rule_index: usize,
+    level: &'static str,
+    message: Message,
+    locations: Vec<Location>,
+}
+
+impl SarifResult {
+    fn new(path: &str, rule_id: String, rule_index: usize, diagnostic: &Diagnostic) -> Self {
+        let range = diagnostic.range;
+        // SARIF lines and columns are 1-based, and the end column is exclusive like LSP's
+        let region = Region {
+            start_line: range.start.line + 1,
+            start_column: range.start.character + 1,
+            end_line: range.end.line + 1,
+            end_column: range.end.character + 1,
+        };
+        Self {
+            rule_id,
+            rule_index,
+            level: level(diagnostic.severity),
+            message: Message {
+                text: diagnostic.message.clone(),
+            },
+            locations: vec![Location::file(path, Some(region))],
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Message {
+    text: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+    physical_location: PhysicalLocation,
+}
+
+impl Location {
+    fn file(path: &str, region: Option<Region>) -> Self {
+        Self {
+            physical_location: PhysicalLocation {
+                artifact_location: ArtifactLocation::for_path(path),
+                region,
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: ArtifactLocation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    region: Option<Region>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ArtifactLocation {
+    uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uri_base_id: Option<&'static str>,
+}
+
+impl ArtifactLocation {
+    /// Locate a file: absolute paths become `file:` URIs, and relative ones are
+    /// resolved against `%SRCROOT%`
+    fn for_path(path: &str) -> Self {
+        let path = path.replace('\\', "/");
+        let uri = if path.starts_with("//") {
+            // UNC path, whose server is the URI's host
+            format!("file:{}", encode_path(&path, false))
+        } else if path.starts_with('/') {
+            format!("file://{}", encode_path(&path, false))
+        } else if has_drive(&path) {
+            format!("file:///{}", encode_path(&path, true))
+        } else {
+            // A colon in a relative reference could be read as a scheme
+            return Self {
+                uri: encode_path(&path, false),
+                uri_base_id: Some(SRCROOT),
+            };
+        };
+        Self {
+            uri,
+            uri_base_id: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+    start_line: u32,
+    start_column: u32,
+    end_line: u32,
+    end_column: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::parse_jenkins_response;
+    use crate::parser::check_jenkinsfile;
+    use serde_json::Value;
+
+    fn render(reports: &[FileReport]) -> Value {
+        let mut out = Vec::new();
+        write(reports, &mut out).unwrap();
+        serde_json::from_slice(&out).unwrap()
+    }
+
+    #[test]
+    fn test_sarif_results_and_rules() {
+        let mut diagnostics = check_jenkinsfile("pipeline {\n}\n");
+        diagnostics.extend(parse_jenkins_response(
+            "WorkflowScript: 3: unexpected token: } @ line 3, column 1.",
+            "",
+        ));
+        let reports = vec![FileReport {
+            path: "ci/My Jenkinsfile".to_string(),
+            diagnostics,
+            failure: None,
+        }];
+
+        let log = render(&reports);
+        assert_eq!(log["version"], "2.1.0");
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "jenkinsfile-ls");
+        assert_eq!(run["invocations"][0]["executionSuccessful"], true);
+
+        let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0]["id"], "missing-section");
+        assert_eq!(
+            rules[0]["helpUri"],
+            "https://www.jenkins.io/doc/book/pipeline/syntax/#agent"
+        );
+        assert_eq!(rules[1]["id"], "unexpected-token");
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0]["ruleIndex"], 0);
+        assert_eq!(results[2]["ruleIndex"], 1);
+        assert_eq!(results[2]["level"], "error");
+        assert_eq!(results[2]["message"]["text"], "unexpected token: }");
+        let location = &results[2]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "ci/My%20Jenkinsfile");
+        assert_eq!(location["region"]["startLine"], 3);
+        assert_eq!(location["region"]["startColumn"], 1);
+    }
+
+    #[test]
+    fn test_sarif_failures_become_notifications() {
+        let reports = vec![FileReport {
+            path: "Jenkinsfile".to_string(),
+            diagnostics: Vec::new(),
+            failure: Some("Network error: connection refused".to_string()),
+        }];
+
+        let log = render(&reports);
+        let invocation = &log["runs"][0]["invocations"][0];
+        assert_eq!(invocation["executionSuccessful"], false);
+        let notification = &invocation["toolExecutionNotifications"][0];
+        assert_eq!(notification["level"], "error");
+        assert_eq!(
+            notification["message"]["text"],
+            "Network error: connection refused"
+        );
+        assert!(log["runs"][0]["results"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sarif_locations() {
+        let report = |path: &str| FileReport {
+            path: path.to_string(),
+            diagnostics: Vec::new(),
+            failure: Some("failed".to_string()),
+        };
+        let reports = vec![
+            report("ci/Jenkinsfile"),
+            report("/srv/repo/My Jenkinsfile"),
+            report(r"C:\repo\Jenkinsfile"),
+            report(r"\\server\share\Jenkinsfile"),
+        ];
+
+        let log = serde_json::to_value(build_log(&reports, Some(Path::new("/srv/repo")))).unwrap();
+        let run = &log["runs"][0];
+        assert_eq!(
+            run["originalUriBaseIds"]["%SRCROOT%"]["uri"],
+            "file:///srv/repo/"
+        );
+        let locations: Vec<&Value> = run["invocations"][0]["toolExecutionNotifications"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|n| &n["locations"][0]["physicalLocation"]["artifactLocation"])
+            .collect();
+        assert_eq!(locations[0]["uri"], "ci/Jenkinsfile");
+        assert_eq!(locations[0]["uriBaseId"], "%SRCROOT%");
+        assert_eq!(locations[1]["uri"], "file:///srv/repo/My%20Jenkinsfile");
+        assert!(locations[1].get("uriBaseId").is_none());
+        assert_eq!(locations[2]["uri"], "file:///C:/repo/Jenkinsfile");
+        assert_eq!(locations[3]["uri"], "file://server/share/Jenkinsfile");
+    }
+}
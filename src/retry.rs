@@ -0,0 +1,152 @@
+use crate::config::RetryConfig;
+use crate::types::{LspError, Result};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Delay before retry number `attempt` (counting from 0)
+///
+/// The delay doubles with each attempt up to the configured maximum, and a random
+/// half of it is dropped so that clients retrying together spread out.
+pub fn backoff_delay(config: &RetryConfig, attempt: u32) -> Duration {
+    let ceiling = config
+        .backoff_ms
+        .saturating_mul(1 << attempt.min(16))
+        .min(config.max_backoff_ms);
+    let half = ceiling / 2;
+    Duration::from_millis(half + fastrand::u64(0..=ceiling - half))
+}
+
+/// Stops requests to Jenkins after too many consecutive failures
+///
+/// Once `failure_threshold` requests in a row have failed, the circuit opens and
+/// requests fail immediately for `cooldown_secs`. After that, requests are let
+/// through again; the first failure reopens the circuit and the first success
+/// closes it.
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: &RetryConfig) -> Self {
+        Self {
+            failure_threshold: config.failure_threshold.max(1),
+            cooldown: Duration::from_secs(config.cooldown_secs),
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    /// Fail fast if the circuit is open
+    pub fn check(&self) -> Result<()> {
+        let state = self.state.lock().unwrap();
+        match state.open_until {
+            Some(until) if Instant::now() < until => Err(LspError::Unavailable(format!(
+                "{} requests in a row failed, trying again in {}s",
+                state.consecutive_failures,
+                until.saturating_duration_since(Instant::now()).as_secs() + 1
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether Jenkins has failed often enough to be treated as down
+    pub fn is_open(&self) -> bool {
+        self.state.lock().unwrap().consecutive_failures >= self.failure_threshold
+    }
+
+    /// Record a request that reached Jenkins, closing the circuit
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.consecutive_failures >= self.failure_threshold {
+            tracing::info!("Jenkins is responding again");
+        }
+        *state = BreakerState::default();
+    }
+
+    /// Record a failed request, opening the circuit if there have been too many
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= self.failure_threshold {
+            if state.consecutive_failures == self.failure_threshold {
+                tracing::warn!(
+                    "{} requests to Jenkins failed in a row, pausing requests for {}s",
+                    state.consecutive_failures,
+                    self.cooldown.as_secs()
+                );
+            }
+            state.open_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_maximum() {
+        let config = RetryConfig {
+            backoff_ms: 100,
+            max_backoff_ms: 1000,
+            ..Default::default()
+        };
+        for (attempt, ceiling) in [
+            (0, 100),
+            (1, 200),
+            (2, 400),
+            (3, 800),
+            (4, 1000),
+            (40, 1000),
+        ] {
+            let delay = backoff_delay(&config, attempt).as_millis() as u64;
+            assert!(
+                (ceiling / 2..=ceiling).contains(&delay),
+                "attempt {}: {}ms",
+                attempt,
+                delay
+            );
+        }
+    }
+
+    #[test]
+    fn test_breaker_opens_after_threshold() {
+        let breaker = CircuitBreaker::new(&RetryConfig {
+            failure_threshold: 2,
+            cooldown_secs: 60,
+            ..Default::default()
+        });
+
+        breaker.record_failure();
+        assert!(!breaker.is_open());
+        assert!(breaker.check().is_ok());
+
+        breaker.record_failure();
+        assert!(breaker.is_open());
+        assert!(matches!(breaker.check(), Err(LspError::Unavailable(_))));
+
+        breaker.record_success();
+        assert!(!breaker.is_open());
+        assert!(breaker.check().is_ok());
+    }
+
+    #[test]
+    fn test_breaker_lets_requests_through_after_cooldown() {
+        let breaker = CircuitBreaker::new(&RetryConfig {
+            failure_threshold: 1,
+            cooldown_secs: 0,
+            ..Default::default()
+        });
+
+        breaker.record_failure();
+        assert!(breaker.is_open());
+        assert!(breaker.check().is_ok());
+    }
+}
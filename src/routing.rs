@@ -0,0 +1,322 @@
+use crate::cache::ValidationCache;
+use crate::config::Config;
+use crate::discovery::build_glob_set;
+use crate::jenkins::JenkinsClient;
+use crate::types::Result;
+use dashmap::DashMap;
+use globset::GlobSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Jenkins clients for the configured instances, and which files each one validates
+#[derive(Default)]
+pub struct JenkinsRouter {
+    /// Client for the top-level instance, used for files no named instance claims
+    default: Option<Arc<JenkinsClient>>,
+    /// Named instances, in the order they were configured
+    routes: Vec<Route>,
+    /// Normalised git remotes of each directory files were routed from, so that git
+    /// config is read once per directory rather than on every validation
+    remotes: DashMap<PathBuf, Arc<Vec<String>>>,
+}
+
+/// A named instance and the files it validates
+struct Route {
+    name: String,
+    /// Globs for the absolute paths of files
+    paths: GlobSet,
+    /// Globs for the normalised remote URLs of the file's git repository
+    remotes: GlobSet,
+    client: Arc<JenkinsClient>,
+}
+
+impl JenkinsRouter {
+    /// Create a client for each configured instance, all sharing one validation cache
+    pub fn new(config: Config) -> Result<Self> {
+        let cache = config
+            .cache
+            .enabled
+            .then(|| Arc::new(ValidationCache::new(&config.cache)));
+
+        let routes = config
+            .instances
+            .iter()
+            .map(|instance| {
+                let remotes: Vec<String> = instance
+                    .remotes
+                    .iter()
+                    .map(|remote| normalize_remote(remote))
+                    .collect();
+                Ok(Route {
+                    name: instance.name.clone(),
+                    paths: build_glob_set(&instance.paths)?,
+                    remotes: build_glob_set(&remotes)?,
+                    client: Arc::new(JenkinsClient::new(
+                        config.for_instance(instance),
+                        cache.clone(),
+                    )?),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let default = if config.has_default_instance() {
+            Some(Arc::new(JenkinsClient::new(config.for_default(), cache)?))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            default,
+            routes,
+            remotes: DashMap::new(),
+        })
+    }
+
+    /// Whether no Jenkins instance is configured at all
+    pub fn is_empty(&self) -> bool {
+        self.default.is_none() && self.routes.is_empty()
+    }
+
+    /// Pick the client for a file
+    ///
+    /// That is the first named instance whose paths or remotes match, or else the
+    /// default instance. Files without a path (e.g. stdin) always use the default.
+    pub fn client_for(&self, path: Option<&Path>) -> Option<Arc<JenkinsClient>> {
+        if let Some(path) = path
+            && !self.routes.is_empty()
+        {
+            let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+            let remotes = self.remotes_of(&path);
+            for route in &self.routes {
+                if route.paths.is_match(&path)
+                    || remotes.iter().any(|remote| route.remotes.is_match(remote))
+                {
+                    tracing::debug!(
+                        "Using Jenkins instance '{}' for {}",
+                        route.name,
+                        path.display()
+                    );
+                    return Some(route.client.clone());
+                }
+            }
+        }
+        self.default.clone()
+    }
+
+    /// Normalised remote URLs of the git repository containing a file
+    fn remotes_of(&self, path: &Path) -> Arc<Vec<String>> {
+        let dir = path.parent().unwrap_or(path).to_path_buf();
+        self.remotes
+            .entry(dir)
+            .or_insert_with(|| {
+                let remotes = git_remotes(path)
+                    .iter()
+                    .map(|remote| normalize_remote(remote))
+                    .collect();
+                Arc::new(remotes)
+            })
+            .clone()
+    }
+
+    /// Every configured client, default first
+    pub fn clients(&self) -> impl Iterator<Item = &Arc<JenkinsClient>> {
+        self.default
+            .iter()
+            .chain(self.routes.iter().map(|route| &route.client))
+    }
+}
+
+/// Reduce a git remote URL to `host/path`, so that HTTPS and SSH forms match alike
+///
+/// For example `git@github.com:org/repo.git` and `https://user@github.com/org/repo`
+/// both become `github.com/org/repo`.
+fn normalize_remote(remote: &str) -> String {
+    let remote = remote.trim().trim_end_matches('/');
+    let remote = remote.strip_suffix(".git").unwrap_or(remote);
+
+    let (authority, path) = match remote.split_once("://") {
+        Some((_, rest)) => rest.split_once('/').unwrap_or((rest, "")),
+        // scp-like syntax: [user@]host:path
+        None => remote.split_once(':').unwrap_or((remote, "")),
+    };
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    let host = host.split(':').next().unwrap_or(host);
+
+    if path.is_empty() {
+        host.to_string()
+    } else {
+        format!("{}/{}", host, path.trim_start_matches('/'))
+    }
+}
+
+/// Remote URLs of the git repository containing `path`, if any
+fn git_remotes(path: &Path) -> Vec<String> {
+    let Some(git_dir) = path.ancestors().skip(1).find_map(find_git_dir) else {
+        return Vec::new();
+    };
+
+    // Worktrees keep their config in the main repository's git directory
+    let common_dir = std::fs::read_to_string(git_dir.join("commondir"))
+        .map(|common| git_dir.join(common.trim()))
+        .unwrap_or(git_dir);
+
+    match std::fs::read_to_string(common_dir.join("config")) {
+        Ok(config) => parse_remote_urls(&config),
+        Err(e) => {
+            tracing::debug!("Cannot read git config in {}: {}", common_dir.display(), e);
+            Vec::new()
+        }
+    }
+}
+
+/// The git directory of a repository whose working tree is `dir`, if it is one
+fn find_git_dir(dir: &Path) -> Option<PathBuf> {
+    let dot_git = dir.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    // Worktrees and submodules have a `.git` file pointing at the git directory
+    let contents = std::fs::read_to_string(&dot_git).ok()?;
+    let git_dir = contents.strip_prefix("gitdir:")?.trim();
+    Some(dir.join(git_dir))
+}
+
+/// The `url` of every `[remote "..."]` section in a git config file
+fn parse_remote_urls(config: &str) -> Vec<String> {
+    let mut urls = Vec::new();
+    let mut in_remote = false;
+    for line in config.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_remote = line.starts_with("[remote ");
+        } else if in_remote
+            && let Some((key, value)) = line.split_once('=')
+            && key.trim() == "url"
+        {
+            urls.push(value.trim().trim_matches('"').to_string());
+        }
+    }
+    urls
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::InstanceConfig;
+    use std::fs;
+
+    #[test]
+    fn test_normalize_remote() {
+        for remote in [
+            "git@github.com:org/repo.git",
+            "https://github.com/org/repo",
+            "https://user@github.com/org/repo.git/",
+            "ssh://git@github.com:22/org/repo.git",
+        ] {
+            assert_eq!(
+                normalize_remote(remote),
+                "github.com/org/repo",
+                "{}",
+                remote
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_remote_urls() {
+        let config = r#"
+[core]
+	bare = false
+[remote "origin"]
+	url = git@github.com:org/repo.git
+	fetch = +refs/heads/*:refs/remotes/origin/*
+[branch "main"]
+	remote = origin
+[remote "upstream"]
+	url = https://github.com/upstream/repo
+"#;
+        assert_eq!(
+            parse_remote_urls(config),
+            vec![
+                "git@github.com:org/repo.git",
+                "https://github.com/upstream/repo"
+            ]
+        );
+    }
+
+    fn instance(name: &str, paths: &[&str], remotes: &[&str]) -> InstanceConfig {
+        InstanceConfig {
+            name: name.to_string(),
+            jenkins_url: format!("https://{}.example.com", name),
+            username: "user".to_string(),
+            api_token: "token".to_string(),
+            paths: paths.iter().map(|p| p.to_string()).collect(),
+            remotes: remotes.iter().map(|r| r.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_client_for_routes_by_path_and_remote() {
+        let dir = tempfile::tempdir().unwrap();
+        let platform = dir.path().join("platform");
+        fs::create_dir_all(platform.join(".git")).unwrap();
+        fs::write(
+            platform.join(".git/config"),
+            "[remote \"origin\"]\n\turl = git@github.com:platform/build.git\n",
+        )
+        .unwrap();
+        let web = dir.path().join("web");
+
+        let router = JenkinsRouter::new(Config {
+            jenkins_url: "https://default.example.com".to_string(),
+            username: "user".to_string(),
+            api_token: "token".to_string(),
+            instances: vec![
+                instance("platform", &[], &["github.com/platform/*"]),
+                instance("web", &[&format!("{}/**", web.display())], &[]),
+            ],
+            ..Default::default()
+        })
+        .unwrap();
+
+        let url = |path: Option<&Path>| router.client_for(path).unwrap().url().to_string();
+        assert_eq!(
+            url(Some(&platform.join("ci/Jenkinsfile"))),
+            "https://platform.example.com"
+        );
+        assert_eq!(
+            url(Some(&web.join("Jenkinsfile"))),
+            "https://web.example.com"
+        );
+        assert_eq!(
+            url(Some(&dir.path().join("Jenkinsfile"))),
+            "https://default.example.com"
+        );
+        assert_eq!(url(None), "https://default.example.com");
+        assert_eq!(router.clients().count(), 3);
+
+        // Remotes are read once per directory
+        fs::remove_file(platform.join(".git/config")).unwrap();
+        assert_eq!(
+            url(Some(&platform.join("ci/Jenkinsfile"))),
+            "https://platform.example.com"
+        );
+    }
+
+    #[test]
+    fn test_no_default_instance() {
+        let router = JenkinsRouter::new(Config {
+            instances: vec![instance("web", &["/srv/web/**"], &[])],
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(!router.is_empty());
+        assert!(
+            router
+                .client_for(Some(Path::new("/srv/api/Jenkinsfile")))
+                .is_none()
+        );
+        assert!(JenkinsRouter::default().is_empty());
+    }
+}
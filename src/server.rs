@@ -1,29 +1,344 @@
+use crate::config::{
+    Config, DiscoveryConfig, EditorSettings, ValidationConfig, config_files, find_project_config,
+    table_from_json,
+};
 use crate::diagnostics::parse_jenkins_response;
+use crate::discovery::{Discovery, validate_files};
+use crate::document::Document;
 use crate::jenkins::JenkinsClient;
-use crate::types::{LspError, ValidationResult};
-use dashmap::DashMap;
-use std::sync::Arc;
+use crate::parser::check_jenkinsfile;
+use crate::routing::JenkinsRouter;
+use crate::types::{LspError, Result as LspResult, ValidationResult};
+use dashmap::{DashMap, DashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::task::AbortHandle;
 use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer};
 
+/// How often to check whether an unreachable Jenkins is back
+const REMOTE_PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often to check the configuration files for changes
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Section of the editor's settings holding ours
+const SETTINGS_SECTION: &str = "jenkinsfile-ls";
+
+/// Diagnostics last computed for an open document, kept for clients that pull them
+struct PulledDiagnostics {
+    /// Hash of the content the diagnostics are for
+    content_hash: u64,
+    /// Identifies this set of diagnostics, so the client needn't be sent it again
+    result_id: String,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl PulledDiagnostics {
+    fn new(content: &str, diagnostics: Vec<Diagnostic>) -> Self {
+        let content_hash = content_hash(content);
+        // The same content can have different diagnostics, e.g. once Jenkins has checked it
+        let mut hasher = DefaultHasher::new();
+        content_hash.hash(&mut hasher);
+        serde_json::to_string(&diagnostics)
+            .unwrap_or_default()
+            .hash(&mut hasher);
+        Self {
+            content_hash,
+            result_id: format!("{:016x}", hasher.finish()),
+            diagnostics,
+        }
+    }
+}
+
+/// Hash of a document's content, used to tell whether it changed since it was validated
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Settings from the configuration, replaced as a whole when it is reloaded
+pub struct Settings {
+    /// Jenkins API clients, and which documents each one validates
+    pub jenkins: JenkinsRouter,
+    /// When to validate documents
+    pub validation: ValidationConfig,
+    /// Which files in the workspace are pipelines
+    pub discovery: DiscoveryConfig,
+    /// Files the credentials were read from, watched along with the config files
+    pub credential_files: Vec<PathBuf>,
+    /// Settings from a project or the editor's settings that were ignored, and why
+    pub ignored: Vec<String>,
+}
+
+impl Settings {
+    /// Create the Jenkins clients for a configuration
+    pub fn from_config(config: Config) -> LspResult<Self> {
+        let validation = config.validation.clone();
+        let discovery = config.discovery.clone();
+        let credential_files = config.credential_files();
+        let ignored = config.ignored.clone();
+        Ok(Self {
+            jenkins: JenkinsRouter::new(config)?,
+            validation,
+            discovery,
+            credential_files,
+            ignored,
+        })
+    }
+
+    /// Settings for running only local checks, when Jenkins isn't configured
+    pub fn local_only() -> Self {
+        let mut validation = ValidationConfig::default();
+        validation.apply_env();
+        Self {
+            jenkins: JenkinsRouter::default(),
+            validation,
+            discovery: DiscoveryConfig::default(),
+            credential_files: Vec::new(),
+            ignored: Vec::new(),
+        }
+    }
+}
+
 /// LSP backend for Jenkinsfile validation
+#[derive(Clone)]
 pub struct Backend {
     /// LSP client for sending notifications and diagnostics
     client: Client,
-    /// Jenkins API client
-    jenkins_client: Arc<JenkinsClient>,
-    /// Document cache mapping URI to (content, version)
-    document_map: Arc<DashMap<Url, (String, i32)>>,
+    /// Current settings; tasks take a snapshot, so a reload doesn't affect them midway
+    settings: Arc<RwLock<Arc<Settings>>>,
+    /// URLs of Jenkins instances that were unreachable last time we tried; only local
+    /// checks run for their documents until they are back
+    unavailable: Arc<DashSet<String>>,
+    /// Open documents, kept in sync through incremental changes
+    document_map: Arc<DashMap<Url, Document>>,
+    /// Debounced validations mapping URI to (generation, task handle)
+    pending_validations: Arc<DashMap<Url, (u64, AbortHandle)>>,
+    /// Generation of the next scheduled validation, so a finished task only removes its own entry
+    next_validation: Arc<AtomicU64>,
+    /// Workspace folders reported by the client
+    workspace_folders: Arc<RwLock<Vec<PathBuf>>>,
+    /// Pipeline files found in the workspace, whose diagnostics we publish even when closed
+    workspace_files: Arc<DashSet<Url>>,
+    /// Whether the client pulls diagnostics for open documents rather than having them pushed
+    pull_diagnostics: Arc<AtomicBool>,
+    /// Latest diagnostics for open documents, when the client pulls them
+    pulled_diagnostics: Arc<DashMap<Url, PulledDiagnostics>>,
+    /// Settings the editor sent as `initializationOptions`
+    initialization_options: Arc<RwLock<Option<toml::Table>>>,
+    /// Settings the editor sent since, which may come from the workspace
+    editor_settings: Arc<RwLock<Option<toml::Table>>>,
+    /// Editor settings the current settings were last loaded with
+    applied_editor_settings: Arc<RwLock<EditorSettings>>,
+    /// Whether the client answers `workspace/configuration` requests
+    configuration_supported: Arc<AtomicBool>,
+    /// Whether the client needs us to register for `workspace/didChangeConfiguration`
+    dynamic_configuration: Arc<AtomicBool>,
 }
 
 impl Backend {
-    /// Create a new LSP backend with the given Jenkins client
-    pub fn new(client: Client, jenkins_client: JenkinsClient) -> Self {
+    /// Create a new LSP backend with the given settings
+    ///
+    /// Without any Jenkins clients, only local checks are run.
+    pub fn new(client: Client, settings: Settings) -> Self {
         Self {
             client,
-            jenkins_client: Arc::new(jenkins_client),
+            settings: Arc::new(RwLock::new(Arc::new(settings))),
+            unavailable: Arc::new(DashSet::new()),
             document_map: Arc::new(DashMap::new()),
+            pending_validations: Arc::new(DashMap::new()),
+            next_validation: Arc::new(AtomicU64::new(0)),
+            workspace_folders: Arc::new(RwLock::new(Vec::new())),
+            workspace_files: Arc::new(DashSet::new()),
+            pull_diagnostics: Arc::new(AtomicBool::new(false)),
+            pulled_diagnostics: Arc::new(DashMap::new()),
+            initialization_options: Arc::new(RwLock::new(None)),
+            editor_settings: Arc::new(RwLock::new(None)),
+            applied_editor_settings: Arc::new(RwLock::new(EditorSettings::default())),
+            configuration_supported: Arc::new(AtomicBool::new(false)),
+            dynamic_configuration: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// The current settings
+    fn settings(&self) -> Arc<Settings> {
+        self.settings.read().unwrap().clone()
+    }
+
+    /// The editor's settings for us
+    ///
+    /// If the client supports it, its current settings are requested first.
+    async fn editor_settings(&self) -> EditorSettings {
+        if self.configuration_supported.load(Ordering::Acquire) {
+            let item = ConfigurationItem {
+                scope_uri: None,
+                section: Some(SETTINGS_SECTION.to_string()),
+            };
+            match self.client.configuration(vec![item]).await {
+                Ok(values) => {
+                    if let Some(settings) = values.first().and_then(table_from_json) {
+                        *self.editor_settings.write().unwrap() = Some(settings);
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to request the editor's settings: {}", e),
+            }
+        }
+
+        EditorSettings {
+            initialization_options: self.initialization_options.read().unwrap().clone(),
+            settings: self.editor_settings.read().unwrap().clone(),
+        }
+    }
+
+    /// Rebuild the settings from the configuration files and the editor's settings
+    ///
+    /// If the configuration is invalid, the error is shown and the current settings
+    /// are kept. Returns whether the settings were replaced.
+    async fn reload_settings(&self, editor: EditorSettings) -> bool {
+        *self.applied_editor_settings.write().unwrap() = editor.clone();
+        let root = self.workspace_folders.read().unwrap().first().cloned();
+        // Credential commands can take a while, so keep them off the async workers
+        let load = tokio::task::spawn_blocking(move || {
+            Config::load_with_editor(None, editor, root.as_deref()).and_then(Settings::from_config)
+        });
+        let loaded = load.await.unwrap_or_else(|e| {
+            Err(LspError::Config(format!(
+                "Loading the configuration failed: {}",
+                e
+            )))
+        });
+        match loaded {
+            Ok(settings) => {
+                tracing::info!(
+                    "Configuration loaded, {} Jenkins client(s) initialized",
+                    settings.jenkins.clients().count()
+                );
+                // Otherwise credentials set in the wrong place would silently not be used
+                if !settings.ignored.is_empty() {
+                    self.client
+                        .show_message(
+                            MessageType::WARNING,
+                            format!("Ignoring {}", settings.ignored.join("; ")),
+                        )
+                        .await;
+                }
+                *self.settings.write().unwrap() = Arc::new(settings);
+                // The new clients haven't failed yet
+                self.unavailable.clear();
+                true
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load configuration: {}", e);
+                self.client
+                    .show_message(
+                        MessageType::WARNING,
+                        format!(
+                            "Failed to load configuration, keeping the previous one: {}",
+                            e
+                        ),
+                    )
+                    .await;
+                false
+            }
+        }
+    }
+
+    /// Files the current settings were read from
+    fn watched_files(&self) -> Vec<PathBuf> {
+        let root = self.workspace_folders.read().unwrap().first().cloned();
+        let mut files = config_files(None, root.as_deref());
+        files.extend(self.settings().credential_files.iter().cloned());
+        files
+    }
+
+    /// Reload the settings whenever the files they were read from change
+    ///
+    /// Files are polled, since the user's config file is usually outside the
+    /// workspace, where editors won't watch it for us.
+    async fn watch_config_files(&self) {
+        let mut files = self.watched_files();
+        let mut previous = fingerprint(&files);
+        loop {
+            tokio::time::sleep(CONFIG_POLL_INTERVAL).await;
+            let current = fingerprint(&files);
+            if current == previous {
+                // Workspace folders may have changed which project config applies
+                let latest = self.watched_files();
+                if latest != files {
+                    files = latest;
+                    previous = fingerprint(&files);
+                }
+                continue;
+            }
+
+            tracing::info!("Configuration files changed, reloading");
+            let editor = self.editor_settings().await;
+            if self.reload_settings(editor).await {
+                self.revalidate_all();
+            }
+            files = self.watched_files();
+            previous = fingerprint(&files);
+        }
+    }
+
+    /// Validate every open document again, and the workspace if enabled
+    fn revalidate_all(&self) {
+        let documents: Vec<(Url, i32)> = self
+            .document_map
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.version()))
+            .collect();
+        for (uri, version) in documents {
+            self.schedule_validation(uri, version);
+        }
+
+        if self.settings().validation.workspace {
+            let folders = self.workspace_folders.read().unwrap().clone();
+            self.spawn_workspace_validation(folders);
+        }
+    }
+
+    /// Schedule a validation of the document after the debounce delay
+    ///
+    /// Any validation still pending or in flight for the same URI is cancelled,
+    /// since its result would be for an outdated version anyway.
+    fn schedule_validation(&self, uri: Url, version: i32) {
+        let delay = Duration::from_millis(self.settings().validation.debounce_ms);
+        let backend = self.clone();
+        let task_uri = uri.clone();
+        let generation = self.next_validation.fetch_add(1, Ordering::Relaxed);
+        // The task waits until its handle is recorded, so it can't remove the entry first
+        let (recorded, wait_recorded) = tokio::sync::oneshot::channel::<()>();
+
+        let task = tokio::spawn(async move {
+            let _ = wait_recorded.await;
+            tokio::time::sleep(delay).await;
+            backend.validate_document(task_uri.clone()).await;
+            backend
+                .pending_validations
+                .remove_if(&task_uri, |_, (scheduled, _)| *scheduled == generation);
+        });
+
+        if let Some((_, handle)) = self
+            .pending_validations
+            .insert(uri, (generation, task.abort_handle()))
+        {
+            tracing::debug!("Cancelling pending validation before version {}", version);
+            handle.abort();
+        }
+        let _ = recorded.send(());
+    }
+
+    /// Cancel any debounced validation pending or in flight for the document
+    fn cancel_pending_validation(&self, uri: &Url) {
+        if let Some((_, (_, handle))) = self.pending_validations.remove(uri) {
+            handle.abort();
         }
     }
 
@@ -31,7 +346,7 @@ impl Backend {
     async fn validate_document(&self, uri: Url) {
         // Get document content and version from cache (snapshot)
         let (content, version) = match self.document_map.get(&uri) {
-            Some(entry) => entry.clone(),
+            Some(document) => (document.text().to_string(), document.version()),
             None => {
                 tracing::warn!("Document not found in cache: {}", uri);
                 return;
@@ -40,49 +355,56 @@ impl Backend {
 
         tracing::info!("Validating document: {} (version {})", uri, version);
 
-        // Perform validation
-        match self.jenkins_client.validate(&content).await {
-            Ok(ValidationResult::Success) => {
-                tracing::info!("Validation successful: {}", uri);
+        // Local checks need no network, so publish them without waiting for Jenkins
+        let local_diagnostics = check_jenkinsfile(&content);
+        // Pulls already run the local checks themselves, so the client isn't asked to refresh
+        self.report_diagnostics(
+            uri.clone(),
+            &content,
+            local_diagnostics.clone(),
+            version,
+            false,
+        )
+        .await;
+
+        let path = uri.to_file_path().ok();
+        let Some(jenkins_client) = self.settings().jenkins.client_for(path.as_deref()) else {
+            return;
+        };
+        if self.unavailable.contains(jenkins_client.url()) {
+            tracing::debug!("Jenkins unreachable, skipping remote validation: {}", uri);
+            return;
+        }
 
+        // Perform remote validation
+        match jenkins_client.validate(&content).await {
+            Ok(result) => {
                 // Check if document version is still current before publishing
                 if let Some(current) = self.document_map.get(&uri)
-                    && current.1 != version
+                    && current.version() != version
                 {
                     tracing::debug!(
                         "Discarding stale diagnostics for {} (validated v{}, current v{})",
                         uri,
                         version,
-                        current.1
+                        current.version()
                     );
                     return;
                 }
 
-                // Clear diagnostics
-                self.client
-                    .publish_diagnostics(uri, Vec::new(), Some(version))
-                    .await;
-            }
-            Ok(ValidationResult::Error(response)) => {
-                tracing::info!("Validation returned errors: {}", uri);
-
-                // Check if document version is still current before publishing
-                if let Some(current) = self.document_map.get(&uri)
-                    && current.1 != version
-                {
-                    tracing::debug!(
-                        "Discarding stale diagnostics for {} (validated v{}, current v{})",
-                        uri,
-                        version,
-                        current.1
-                    );
-                    return;
+                // Remote diagnostics are published alongside the local ones
+                let mut diagnostics = local_diagnostics;
+                match result {
+                    ValidationResult::Success => {
+                        tracing::info!("Validation successful: {}", uri);
+                    }
+                    ValidationResult::Error(response) => {
+                        tracing::info!("Validation returned errors: {}", uri);
+                        diagnostics.extend(parse_jenkins_response(&response, &content));
+                    }
                 }
 
-                // Parse errors and publish diagnostics
-                let diagnostics = parse_jenkins_response(&response);
-                self.client
-                    .publish_diagnostics(uri, diagnostics, Some(version))
+                self.report_diagnostics(uri, &content, diagnostics, version, true)
                     .await;
             }
             Err(LspError::Auth(msg)) => {
@@ -94,6 +416,15 @@ impl Backend {
                     )
                     .await;
             }
+            Err(e @ (LspError::Network(_) | LspError::Unavailable(_))) => {
+                // The client already retried; only once failures persist is Jenkins
+                // treated as down, with one message rather than one per save
+                tracing::warn!("Jenkins unreachable: {}", e);
+                if jenkins_client.is_degraded() {
+                    self.mark_remote_unavailable(&jenkins_client, &e.to_string())
+                        .await;
+                }
+            }
             Err(e) => {
                 tracing::error!("Validation error: {}", e);
                 self.client
@@ -102,18 +433,293 @@ impl Backend {
             }
         }
     }
+
+    /// Make diagnostics for an open document available to the client
+    ///
+    /// Clients using the push model are sent them straight away. Otherwise they are
+    /// kept until the client pulls them, and if `refresh` is set and they differ from
+    /// the ones kept before, the client is asked to do so.
+    async fn report_diagnostics(
+        &self,
+        uri: Url,
+        content: &str,
+        diagnostics: Vec<Diagnostic>,
+        version: i32,
+        refresh: bool,
+    ) {
+        if !self.pull_diagnostics.load(Ordering::Acquire) {
+            self.client
+                .publish_diagnostics(uri, diagnostics, Some(version))
+                .await;
+            return;
+        }
+
+        // The document may have been closed while it was being validated
+        if !self.document_map.contains_key(&uri) {
+            return;
+        }
+        let pulled = PulledDiagnostics::new(content, diagnostics);
+        let result_id = pulled.result_id.clone();
+        let previous = self.pulled_diagnostics.insert(uri, pulled);
+        if !refresh || previous.is_some_and(|previous| previous.result_id == result_id) {
+            return;
+        }
+        if let Err(e) = self.client.workspace_diagnostic_refresh().await {
+            tracing::warn!("Failed to ask the client to refresh diagnostics: {}", e);
+        }
+    }
+
+    /// Switch to local-only checks for an instance's documents until it can be reached again
+    ///
+    /// Only the first failure is reported to the user; a background task then
+    /// polls Jenkins and resumes remote validation when it answers.
+    async fn mark_remote_unavailable(&self, jenkins_client: &Arc<JenkinsClient>, reason: &str) {
+        if !self.unavailable.insert(jenkins_client.url().to_string()) {
+            return;
+        }
+
+        self.client
+            .show_message(
+                MessageType::WARNING,
+                format!(
+                    "Jenkins at {} is unreachable, so only local checks are available until it is back: {}",
+                    jenkins_client.url(),
+                    reason
+                ),
+            )
+            .await;
+
+        let backend = self.clone();
+        let jenkins_client = jenkins_client.clone();
+        tokio::spawn(async move { backend.wait_for_remote(jenkins_client).await });
+    }
+
+    /// Poll Jenkins until it is reachable, then schedule revalidation of its open documents
+    async fn wait_for_remote(&self, jenkins_client: Arc<JenkinsClient>) {
+        loop {
+            tokio::time::sleep(REMOTE_PROBE_INTERVAL).await;
+            // Stop if the configuration was reloaded and the client replaced
+            let current = self.settings();
+            if !current
+                .jenkins
+                .clients()
+                .any(|client| Arc::ptr_eq(client, &jenkins_client))
+            {
+                return;
+            }
+            match jenkins_client.ping().await {
+                Ok(()) => break,
+                Err(e) => tracing::debug!("Jenkins still unreachable: {}", e),
+            }
+        }
+
+        tracing::info!("Jenkins at {} is reachable again", jenkins_client.url());
+        self.unavailable.remove(jenkins_client.url());
+        self.client
+            .show_message(
+                MessageType::INFO,
+                format!(
+                    "Jenkins at {} is reachable again, remote validation resumed",
+                    jenkins_client.url()
+                ),
+            )
+            .await;
+
+        let settings = self.settings();
+        let documents: Vec<(Url, i32)> = self
+            .document_map
+            .iter()
+            .filter(|entry| {
+                let path = entry.key().to_file_path().ok();
+                settings
+                    .jenkins
+                    .client_for(path.as_deref())
+                    .is_some_and(|client| Arc::ptr_eq(&client, &jenkins_client))
+            })
+            .map(|entry| (entry.key().clone(), entry.version()))
+            .collect();
+        for (uri, version) in documents {
+            self.schedule_validation(uri, version);
+        }
+    }
+
+    /// Find and validate pipeline files in the given folders in the background
+    fn spawn_workspace_validation(&self, folders: Vec<PathBuf>) {
+        let backend = self.clone();
+        tokio::spawn(async move { backend.validate_workspace(folders).await });
+    }
+
+    /// Find pipeline files in the given folders and validate them from disk
+    async fn validate_workspace(&self, folders: Vec<PathBuf>) {
+        let discovery = match Discovery::new(&self.settings().discovery) {
+            Ok(discovery) => discovery,
+            Err(e) => {
+                tracing::error!("Cannot search workspace: {}", e);
+                self.client
+                    .show_message(
+                        MessageType::ERROR,
+                        format!("Cannot search workspace for Jenkinsfiles: {}", e),
+                    )
+                    .await;
+                return;
+            }
+        };
+
+        let search = tokio::task::spawn_blocking(move || {
+            folders
+                .iter()
+                .flat_map(|folder| discovery.discover(folder))
+                .collect::<Vec<_>>()
+        });
+        let paths = match search.await {
+            Ok(paths) => paths,
+            Err(e) => {
+                tracing::error!("Workspace search failed: {}", e);
+                return;
+            }
+        };
+
+        tracing::info!("Found {} pipeline files in the workspace", paths.len());
+        self.validate_files_on_disk(paths).await;
+    }
+
+    /// Validate files as saved on disk and publish their diagnostics
+    ///
+    /// Files open in the editor are skipped, since their diagnostics come from the
+    /// editor's (possibly unsaved) copy. Failures to reach Jenkins are summarised in
+    /// one message rather than reported per file.
+    async fn validate_files_on_disk(&self, paths: Vec<PathBuf>) {
+        let settings = self.settings();
+        let reports = validate_files(&paths, settings.discovery.concurrency, |path| {
+            settings
+                .jenkins
+                .client_for(Some(path))
+                .filter(|client| !self.unavailable.contains(client.url()))
+        })
+        .await;
+
+        let mut failures = Vec::new();
+        for (path, report) in paths.iter().zip(reports) {
+            let Ok(uri) = Url::from_file_path(path) else {
+                continue;
+            };
+            self.workspace_files.insert(uri.clone());
+
+            if let Some(failure) = report.failure {
+                tracing::warn!("Could not fully validate {}: {}", report.path, failure);
+                failures.push(failure);
+            }
+            if self.document_map.contains_key(&uri) {
+                continue;
+            }
+            self.client
+                .publish_diagnostics(uri, report.diagnostics, None)
+                .await;
+        }
+
+        let Some(first) = failures.first() else {
+            return;
+        };
+        let degraded: Vec<Arc<JenkinsClient>> = settings
+            .jenkins
+            .clients()
+            .filter(|client| client.is_degraded())
+            .cloned()
+            .collect();
+        for jenkins_client in &degraded {
+            self.mark_remote_unavailable(jenkins_client, first).await;
+        }
+        if degraded.is_empty() {
+            self.client
+                .show_message(
+                    MessageType::WARNING,
+                    format!(
+                        "{} workspace file(s) could not be fully validated: {}",
+                        failures.len(),
+                        first
+                    ),
+                )
+                .await;
+        }
+    }
 }
 
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend {
-    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
         tracing::info!("Initializing Jenkinsfile LSP server");
 
+        // Older clients only send a root URI rather than workspace folders
+        #[allow(deprecated)]
+        let folder_uris = match params.workspace_folders {
+            Some(folders) => folders.into_iter().map(|f| f.uri).collect(),
+            None => params.root_uri.into_iter().collect::<Vec<_>>(),
+        };
+        *self.workspace_folders.write().unwrap() = folder_uris
+            .iter()
+            .filter_map(|uri| uri.to_file_path().ok())
+            .collect();
+
+        // Pulled diagnostics only update when the client asks, so we must be able to
+        // prompt it once a validation finishes in the background
+        let capabilities = &params.capabilities;
+        let pull_diagnostics = capabilities
+            .text_document
+            .as_ref()
+            .is_some_and(|t| t.diagnostic.is_some())
+            && capabilities
+                .workspace
+                .as_ref()
+                .and_then(|w| w.diagnostic.as_ref())
+                .and_then(|d| d.refresh_support)
+                .unwrap_or(false);
+        self.pull_diagnostics
+            .store(pull_diagnostics, Ordering::Release);
+
+        if let Some(options) = &params.initialization_options {
+            *self.initialization_options.write().unwrap() =
+                table_from_json(settings_section(options));
+        }
+        let configuration_supported = capabilities
+            .workspace
+            .as_ref()
+            .and_then(|w| w.configuration)
+            .unwrap_or(false);
+        self.configuration_supported
+            .store(configuration_supported, Ordering::Release);
+        let dynamic_configuration = capabilities
+            .workspace
+            .as_ref()
+            .and_then(|w| w.did_change_configuration.as_ref())
+            .and_then(|d| d.dynamic_registration)
+            .unwrap_or(false);
+        self.dynamic_configuration
+            .store(dynamic_configuration, Ordering::Release);
+        tracing::info!(
+            "Diagnostics will be {}",
+            if pull_diagnostics { "pulled" } else { "pushed" }
+        );
+
         Ok(InitializeResult {
             capabilities: ServerCapabilities {
                 text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                    TextDocumentSyncKind::FULL,
+                    TextDocumentSyncKind::INCREMENTAL,
                 )),
+                workspace: Some(WorkspaceServerCapabilities {
+                    workspace_folders: Some(WorkspaceFoldersServerCapabilities {
+                        supported: Some(true),
+                        change_notifications: Some(OneOf::Left(true)),
+                    }),
+                    file_operations: None,
+                }),
+                diagnostic_provider: pull_diagnostics.then(|| {
+                    DiagnosticServerCapabilities::Options(DiagnosticOptions {
+                        identifier: Some("jenkinsfile-ls".to_string()),
+                        inter_file_dependencies: false,
+                        workspace_diagnostics: false,
+                        work_done_progress_options: Default::default(),
+                    })
+                }),
                 ..Default::default()
             },
             server_info: Some(ServerInfo {
@@ -128,6 +734,46 @@ impl LanguageServer for Backend {
         self.client
             .log_message(MessageType::INFO, "Jenkinsfile LSP server initialized")
             .await;
+
+        // Clients only tell us about settings changes we have registered for
+        if self.dynamic_configuration.load(Ordering::Acquire) {
+            let registration = Registration {
+                id: "jenkinsfile-ls-configuration".to_string(),
+                method: "workspace/didChangeConfiguration".to_string(),
+                register_options: None,
+            };
+            if let Err(e) = self.client.register_capability(vec![registration]).await {
+                tracing::warn!("Failed to register for configuration changes: {}", e);
+            }
+        }
+
+        // The configuration loaded at startup has neither the editor's settings nor
+        // the project's
+        let editor = self.editor_settings().await;
+        let root = self.workspace_folders.read().unwrap().first().cloned();
+        if editor != EditorSettings::default()
+            || root.as_deref().and_then(find_project_config).is_some()
+        {
+            self.reload_settings(editor).await;
+        }
+
+        let backend = self.clone();
+        tokio::spawn(async move { backend.watch_config_files().await });
+
+        if self.settings().jenkins.is_empty() {
+            self.client
+                .show_message(
+                    MessageType::WARNING,
+                    "Jenkins is not configured, so only local checks are available. \
+                     Set JENKINS_URL, JENKINS_USER_ID and JENKINS_API_TOKEN to enable remote validation.",
+                )
+                .await;
+        }
+
+        if self.settings().validation.workspace {
+            let folders = self.workspace_folders.read().unwrap().clone();
+            self.spawn_workspace_validation(folders);
+        }
     }
 
     async fn shutdown(&self) -> Result<()> {
@@ -143,7 +789,15 @@ impl LanguageServer for Backend {
         tracing::info!("Document opened: {} (version {})", uri, version);
 
         // Store document content and version
-        self.document_map.insert(uri.clone(), (content, version));
+        self.document_map
+            .insert(uri.clone(), Document::new(content, version));
+
+        // A pulled report replaces the one pushed while the file was closed
+        if self.pull_diagnostics.load(Ordering::Acquire) && self.workspace_files.contains(&uri) {
+            self.client
+                .publish_diagnostics(uri.clone(), Vec::new(), None)
+                .await;
+        }
 
         // Validate immediately on open
         self.validate_document(uri).await;
@@ -153,20 +807,33 @@ impl LanguageServer for Backend {
         let uri = params.text_document.uri;
         let version = params.text_document.version;
 
-        // Update document content (FULL sync, so we take the last change)
-        if let Some(change) = params.content_changes.into_iter().last() {
-            tracing::debug!("Document changed: {} (version {})", uri, version);
-            self.document_map.insert(uri, (change.text, version));
+        // Apply changes in order (INCREMENTAL sync, so each may be a range edit)
+        match self.document_map.get_mut(&uri) {
+            Some(mut document) => {
+                tracing::debug!("Document changed: {} (version {})", uri, version);
+                for change in params.content_changes {
+                    document.apply_change(change);
+                }
+                document.set_version(version);
+            }
+            None => {
+                tracing::warn!("Change for document not found in cache: {}", uri);
+                return;
+            }
         }
 
-        // We don't validate on change, only on save
+        // Unless validating on change is enabled, we only validate on save
+        if self.settings().validation.on_change {
+            self.schedule_validation(uri, version);
+        }
     }
 
     async fn did_save(&self, params: DidSaveTextDocumentParams) {
         let uri = params.text_document.uri;
         tracing::info!("Document saved: {}", uri);
 
-        // Validate on save
+        // Validate on save; this supersedes any pending debounced validation
+        self.cancel_pending_validation(&uri);
         self.validate_document(uri).await;
     }
 
@@ -174,10 +841,197 @@ impl LanguageServer for Backend {
         let uri = params.text_document.uri;
         tracing::info!("Document closed: {}", uri);
 
-        // Remove from cache
+        // Remove from cache, dropping any validation still pending for it
+        self.cancel_pending_validation(&uri);
         self.document_map.remove(&uri);
+        self.pulled_diagnostics.remove(&uri);
+
+        // Workspace files go back to showing diagnostics for their saved contents
+        if self.workspace_files.contains(&uri)
+            && let Ok(path) = uri.to_file_path()
+        {
+            let backend = self.clone();
+            tokio::spawn(async move { backend.validate_files_on_disk(vec![path]).await });
+            return;
+        }
 
         // Clear diagnostics
         self.client.publish_diagnostics(uri, Vec::new(), None).await;
     }
+
+    async fn diagnostic(
+        &self,
+        params: DocumentDiagnosticParams,
+    ) -> Result<DocumentDiagnosticReportResult> {
+        let uri = params.text_document.uri;
+        let content = match self.document_map.get(&uri) {
+            Some(document) => document.text().to_string(),
+            None => {
+                tracing::warn!("Diagnostics requested for unknown document: {}", uri);
+                return Ok(full_report(None, Vec::new()));
+            }
+        };
+
+        if let Some(pulled) = self.pulled_diagnostics.get(&uri)
+            && pulled.content_hash == content_hash(&content)
+        {
+            if params.previous_result_id.as_deref() == Some(pulled.result_id.as_str()) {
+                tracing::debug!("Diagnostics unchanged: {}", uri);
+                return Ok(DocumentDiagnosticReportResult::Report(
+                    DocumentDiagnosticReport::Unchanged(RelatedUnchangedDocumentDiagnosticReport {
+                        related_documents: None,
+                        unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport {
+                            result_id: pulled.result_id.clone(),
+                        },
+                    }),
+                ));
+            }
+            return Ok(full_report(
+                Some(pulled.result_id.clone()),
+                pulled.diagnostics.clone(),
+            ));
+        }
+
+        // Not validated since it last changed: local checks are cheap enough to run on
+        // every pull, but Jenkins is only asked when a validation is triggered
+        Ok(full_report(None, check_jenkinsfile(&content)))
+    }
+
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        // Clients that answer workspace/configuration often send no settings here
+        if let Some(settings) = table_from_json(settings_section(&params.settings))
+            && !settings.is_empty()
+        {
+            *self.editor_settings.write().unwrap() = Some(settings);
+        }
+
+        let editor = self.editor_settings().await;
+        if editor == *self.applied_editor_settings.read().unwrap() {
+            return;
+        }
+        if self.reload_settings(editor).await {
+            self.revalidate_all();
+        }
+    }
+
+    async fn did_change_workspace_folders(&self, params: DidChangeWorkspaceFoldersParams) {
+        let added: Vec<PathBuf> = params
+            .event
+            .added
+            .iter()
+            .filter_map(|f| f.uri.to_file_path().ok())
+            .collect();
+        let removed: Vec<PathBuf> = params
+            .event
+            .removed
+            .iter()
+            .filter_map(|f| f.uri.to_file_path().ok())
+            .collect();
+        tracing::info!("Workspace folders changed: +{:?} -{:?}", added, removed);
+
+        {
+            let mut folders = self.workspace_folders.write().unwrap();
+            folders.retain(|folder| !removed.contains(folder));
+            folders.extend(added.iter().cloned());
+        }
+
+        // Forget files from removed folders, clearing diagnostics unless they're open
+        let forgotten: Vec<Url> = self
+            .workspace_files
+            .iter()
+            .filter(|uri| {
+                uri.to_file_path()
+                    .is_ok_and(|path| removed.iter().any(|folder| path.starts_with(folder)))
+            })
+            .map(|uri| uri.clone())
+            .collect();
+        for uri in forgotten {
+            self.workspace_files.remove(&uri);
+            if !self.document_map.contains_key(&uri) {
+                self.client.publish_diagnostics(uri, Vec::new(), None).await;
+            }
+        }
+
+        if self.settings().validation.workspace && !added.is_empty() {
+            self.spawn_workspace_validation(added);
+        }
+    }
+}
+
+/// Hashes of the files' contents (None if unreadable), to tell when any changes
+fn fingerprint(files: &[PathBuf]) -> Vec<Option<u64>> {
+    files
+        .iter()
+        .map(|file| {
+            let contents = std::fs::read(file).ok()?;
+            let mut hasher = DefaultHasher::new();
+            contents.hash(&mut hasher);
+            Some(hasher.finish())
+        })
+        .collect()
+}
+
+/// Our settings, whether sent on their own or within all of the editor's settings
+fn settings_section(settings: &serde_json::Value) -> &serde_json::Value {
+    settings.get(SETTINGS_SECTION).unwrap_or(settings)
+}
+
+/// A pulled diagnostic report containing every diagnostic for the document
+fn full_report(
+    result_id: Option<String>,
+    diagnostics: Vec<Diagnostic>,
+) -> DocumentDiagnosticReportResult {
+    DocumentDiagnosticReportResult::Report(DocumentDiagnosticReport::Full(
+        RelatedFullDocumentDiagnosticReport {
+            related_documents: None,
+            full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                result_id,
+                items: diagnostics,
+            },
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_result_id_tracks_content_and_diagnostics() {
+        let content = "pipeline {\n}\n";
+        let local = check_jenkinsfile(content);
+        let first = PulledDiagnostics::new(content, local.clone());
+        let again = PulledDiagnostics::new(content, local.clone());
+        assert_eq!(first.content_hash, content_hash(content));
+        assert_eq!(first.result_id, again.result_id);
+
+        // Remote diagnostics for the same content give a new result
+        let mut remote = local;
+        remote.extend(parse_jenkins_response(
+            "WorkflowScript: 2: unexpected token: } @ line 2, column 1.",
+            content,
+        ));
+        let validated = PulledDiagnostics::new(content, remote);
+        assert_eq!(validated.content_hash, first.content_hash);
+        assert_ne!(validated.result_id, first.result_id);
+
+        let edited = PulledDiagnostics::new("node {}\n", Vec::new());
+        assert_ne!(edited.content_hash, first.content_hash);
+    }
+
+    #[test]
+    fn test_fingerprint_tracks_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = vec![dir.path().join("config.toml")];
+        let missing = fingerprint(&files);
+        assert_eq!(missing, vec![None]);
+
+        std::fs::write(&files[0], "api_token = \"old\"").unwrap();
+        let old = fingerprint(&files);
+        assert_ne!(old, missing);
+        assert_eq!(fingerprint(&files), old);
+
+        std::fs::write(&files[0], "api_token = \"new\"").unwrap();
+        assert_ne!(fingerprint(&files), old);
+    }
 }
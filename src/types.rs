@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// CSRF crumb returned by Jenkins for authenticated requests
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Crumb {
     /// The crumb value to include in requests
     pub crumb: String,
@@ -12,7 +12,7 @@ pub struct Crumb {
 }
 
 /// Result of validating a Jenkinsfile
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ValidationResult {
     /// Jenkinsfile is valid
     Success,
@@ -35,6 +35,9 @@ pub enum LspError {
     #[error("Authentication failed: {0}")]
     Auth(String),
 
+    #[error("Jenkins unavailable: {0}")]
+    Unavailable(String),
+
     // we never emit this one
     // #[error("Parse error: {0}")]
     // Parse(String),